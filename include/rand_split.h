@@ -0,0 +1,101 @@
+/*
+ * C interface to the rand-split crate's SipRng and ChaskeyRng.  See
+ * the documentation of the crate's `ffi` module.
+ *
+ * This header follows the declarations in src/ffi.rs.  Regenerate it
+ * after changing them with:
+ *
+ *     cbindgen --config cbindgen.toml --output include/rand_split.h
+ */
+
+#ifndef RAND_SPLIT_H
+#define RAND_SPLIT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A splittable pseudorandom generator based on SipHash. */
+typedef struct SipRng SipRng;
+
+/* A PRF taken off a `SipRng`. */
+typedef struct SipPrf SipPrf;
+
+/* A splittable pseudorandom generator based on Chaskey. */
+typedef struct ChaskeyRng ChaskeyRng;
+
+/* A PRF taken off a `ChaskeyRng`. */
+typedef struct ChaskeyPrf ChaskeyPrf;
+
+/* Create a generator from a pointer to a 32-byte seed, or return
+ * null if the pointer is null. */
+SipRng *rand_split_siprng_new(const uint8_t (*seed)[32]);
+
+/* The generator's next `u64`. */
+uint64_t rand_split_siprng_next_u64(SipRng *rng);
+
+/* Fill `len` bytes at `dest` with the generator's output.  The bytes
+ * are the same on every target: those of `next_u64`, little-endian. */
+void rand_split_siprng_fill_bytes(SipRng *rng, uint8_t *dest, size_t len);
+
+/* Split a new generator off this one. */
+SipRng *rand_split_siprng_split(SipRng *rng);
+
+/* Split a PRF off this generator. */
+SipPrf *rand_split_siprng_splitn(SipRng *rng);
+
+/* The PRF's generator at a `u32` index. */
+SipRng *rand_split_sipprf_call(const SipPrf *prf, uint32_t i);
+
+/* The PRF's generator at a `u64` index. */
+SipRng *rand_split_sipprf_call_u64(const SipPrf *prf, uint64_t i);
+
+/* A copy of the generator, in the same state. */
+SipRng *rand_split_siprng_clone(const SipRng *rng);
+
+/* Release a generator. */
+void rand_split_siprng_free(SipRng *rng);
+
+/* Release a PRF. */
+void rand_split_sipprf_free(SipPrf *prf);
+
+/* Create a generator from a pointer to a 32-byte seed, or return
+ * null if the pointer is null. */
+ChaskeyRng *rand_split_chaskeyrng_new(const uint8_t (*seed)[32]);
+
+/* The generator's next `u64`. */
+uint64_t rand_split_chaskeyrng_next_u64(ChaskeyRng *rng);
+
+/* Fill `len` bytes at `dest` with the generator's output.  The bytes
+ * are the same on every target: those of `next_u64`, little-endian. */
+void rand_split_chaskeyrng_fill_bytes(ChaskeyRng *rng, uint8_t *dest, size_t len);
+
+/* Split a new generator off this one. */
+ChaskeyRng *rand_split_chaskeyrng_split(ChaskeyRng *rng);
+
+/* Split a PRF off this generator. */
+ChaskeyPrf *rand_split_chaskeyrng_splitn(ChaskeyRng *rng);
+
+/* The PRF's generator at a `u32` index. */
+ChaskeyRng *rand_split_chaskeyprf_call(const ChaskeyPrf *prf, uint32_t i);
+
+/* The PRF's generator at a `u64` index. */
+ChaskeyRng *rand_split_chaskeyprf_call_u64(const ChaskeyPrf *prf, uint64_t i);
+
+/* A copy of the generator, in the same state. */
+ChaskeyRng *rand_split_chaskeyrng_clone(const ChaskeyRng *rng);
+
+/* Release a generator. */
+void rand_split_chaskeyrng_free(ChaskeyRng *rng);
+
+/* Release a PRF. */
+void rand_split_chaskeyprf_free(ChaskeyPrf *prf);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* RAND_SPLIT_H */
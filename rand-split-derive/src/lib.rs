@@ -0,0 +1,413 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `#[derive(SplitRand)]` and `#[derive(CoSplitRand)]` for structs,
+//! tuple structs and enums.
+//!
+//! The derived `split_rand` splits a PRF off the generator it's
+//! given, and generates each field from the generator that
+//! `SplitPrf::fold_in` produces for that field's name (tuple fields
+//! are named by their position, `"0"`, `"1"` and so on).  So adding,
+//! removing, reordering or changing the type of a field doesn't
+//! change the values generated for the other fields.
+//!
+//! For an enum, a variant is picked at random and its fields are
+//! generated as above, keyed by both the variant's and the field's
+//! name.  Variants are equally likely unless given weights.
+//!
+//! The derived `split_rand_sized` passes its size budget on to every
+//! field unchanged, the way tuples do.  Recursive types need to
+//! spend their budget as they go, so they should implement
+//! `SplitRand` by hand.
+//!
+//! The macro is meant to be used through the `derive` feature of
+//! `rand-split`, which re-exports it alongside the trait:
+//!
+//! ```
+//! extern crate rand_split;
+//!
+//! use rand_split::{SplitRng, SplitRand};
+//! use rand_split::siprng::SipRng;
+//!
+//! fn small<R: SplitRng>(rng: &mut R) -> u8 {
+//!     u8::split_rand(rng) % 10
+//! }
+//!
+//! #[derive(SplitRand)]
+//! struct Particle {
+//!     position: (f64, f64),
+//!     #[split_rand(with = "small")]
+//!     charge: u8,
+//!     #[split_rand(seq)]
+//!     tag: u64,
+//! }
+//!
+//! #[derive(SplitRand)]
+//! enum Shape {
+//!     #[split_rand(weight = 3)]
+//!     Circle { radius: f64 },
+//!     Square(f64),
+//!     #[split_rand(weight = 0)]
+//!     Nothing,
+//! }
+//!
+//! # fn main() {
+//! let mut rng = SipRng::new(1, 2);
+//! let particle: Particle = rng.split_gen();
+//! assert!(particle.charge < 10);
+//! let shape: Shape = rng.split_gen();
+//! # let _ = (particle.position, particle.tag, shape);
+//! # }
+//! ```
+//!
+//! ## Attributes
+//!
+//! * `#[split_rand(weight = N)]` on an enum variant makes it `N`
+//!   times as likely as a variant with the default weight of 1.  A
+//!   weight of 0 means the variant is never generated.
+//! * `#[split_rand(with = "path")]` on a field generates it by
+//!   calling `path(&mut rng)`, where `path` names a function of type
+//!   `fn<R: SplitRng>(&mut R) -> T`.
+//! * `#[split_rand(seq)]` on a field generates it sequentially from
+//!   its `Rand` impl, as with the `Seq` wrapper.
+//!
+//! ## `CoSplitRand`
+//!
+//! The derived `co_split_rand` steers the generator by each field in
+//! turn, in the order they're declared.  For an enum it first steers
+//! by the variant's name, through `SplitRng::fold_in`, so values of
+//! different variants lead to different generators however their
+//! fields compare.  It takes no attributes.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::ext::IdentExt;
+use syn::{Attribute, Data, DeriveInput, Error, Fields, GenericParam,
+          Ident, LitInt, LitStr, Path, Result};
+
+
+#[proc_macro_derive(SplitRand, attributes(split_rand))]
+pub fn derive_split_rand(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(CoSplitRand)]
+pub fn derive_co_split_rand(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_co(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2> {
+    if let Some(attr) = split_rand_attrs(&input.attrs).next() {
+        return Err(Error::new_spanned(
+            attr, "#[split_rand] is not supported on the type itself"));
+    }
+
+    add_bounds(&mut input, parse_quote!(::rand_split::SplitRand));
+
+    let name = &input.ident;
+    let expand_body = |sized| match input.data {
+        Data::Struct(ref data) =>
+            expand_struct(name, &data.fields, sized),
+        Data::Enum(ref data) =>
+            expand_enum(name, data.variants.iter().collect(), sized),
+        Data::Union(_) =>
+            Err(Error::new_spanned(
+                &input.ident, "SplitRand can't be derived for unions")),
+    };
+    let body = expand_body(false)?;
+    let sized_body = expand_body(true)?;
+
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rand_split::SplitRand
+            for #name #ty_generics #where_clause
+        {
+            fn split_rand<__R: ::rand_split::SplitRng>(__rng: &mut __R) -> Self {
+                #body
+            }
+
+            fn split_rand_sized<__R: ::rand_split::SplitRng>(
+                __rng: &mut __R, __size: usize) -> Self
+            {
+                #sized_body
+            }
+        }
+    })
+}
+
+fn expand_struct(name: &Ident, fields: &Fields, sized: bool)
+                 -> Result<TokenStream2>
+{
+    if let Fields::Unit = *fields {
+        return Ok(quote!(#name));
+    }
+    let build = build_fields(quote!(#name), fields, sized,
+                             |field| quote!(#field))?;
+    Ok(quote! {
+        let __prf = ::rand_split::SplitRng::splitn(__rng);
+        #build
+    })
+}
+
+fn expand_enum(name: &Ident, variants: Vec<&syn::Variant>, sized: bool)
+               -> Result<TokenStream2>
+{
+    let mut total: u64 = 0;
+    let mut arms = Vec::new();
+    for variant in variants {
+        let weight = variant_weight(&variant.attrs)?;
+        if weight == 0 {
+            continue;
+        }
+        total = total.checked_add(weight).ok_or_else(|| {
+            Error::new_spanned(variant, "total variant weight overflows u64")
+        })?;
+
+        let ident = &variant.ident;
+        let vname = unraw(ident);
+        let build = build_fields(quote!(#name::#ident), &variant.fields, sized,
+                                 |field| quote!(&(#vname, #field)))?;
+        arms.push(quote! {
+            if __choice < #total {
+                return #build;
+            }
+        });
+    }
+    if total == 0 {
+        return Err(Error::new_spanned(
+            name, "SplitRand needs at least one variant with nonzero weight"));
+    }
+
+    // Scale a uniform `u64` into `0..total` with a multiply and shift,
+    // which is unbiased enough for any realistic total.
+    Ok(quote! {
+        let __prf = ::rand_split::SplitRng::splitn(__rng);
+        let __choice: u64 = ::rand_split::SplitRand::split_rand(
+            &mut ::rand_split::SplitPrf::call(&__prf, 0));
+        let __choice = ((__choice as u128 * #total as u128) >> 64) as u64;
+        #(#arms)*
+        unreachable!()
+    })
+}
+
+/// Build a struct or variant value from `__prf`, one field at a time.
+/// `key` turns the name of a field into the expression it's keyed by.
+/// If `sized` is set, fields get the size budget `__size`.
+fn build_fields<K>(path: TokenStream2, fields: &Fields, sized: bool, key: K)
+                   -> Result<TokenStream2>
+    where K: Fn(LitStr) -> TokenStream2
+{
+    Ok(match *fields {
+        Fields::Named(ref fields) => {
+            let mut inits = Vec::new();
+            for field in &fields.named {
+                let ident = field.ident.as_ref().unwrap();
+                let value = field_value(&field.attrs, key(unraw(ident)), sized)?;
+                inits.push(quote!(#ident: #value));
+            }
+            quote!(#path { #(#inits),* })
+        }
+        Fields::Unnamed(ref fields) => {
+            let mut values = Vec::new();
+            for (i, field) in fields.unnamed.iter().enumerate() {
+                let name = LitStr::new(&i.to_string(), proc_macro2::Span::call_site());
+                values.push(field_value(&field.attrs, key(name), sized)?);
+            }
+            quote!(#path(#(#values),*))
+        }
+        Fields::Unit => quote!(#path),
+    })
+}
+
+/// The expression that generates one field, from the generator that
+/// `fold_in` gives for `key`.  Fields with a custom generator don't
+/// get the size budget.
+fn field_value(attrs: &[Attribute], key: TokenStream2, sized: bool)
+               -> Result<TokenStream2>
+{
+    let rng = quote!(&mut ::rand_split::SplitPrf::fold_in(&__prf, #key));
+    Ok(match field_attrs(attrs)? {
+        FieldAttrs::Default if sized =>
+            quote!(::rand_split::SplitRand::split_rand_sized(#rng, __size)),
+        FieldAttrs::Default =>
+            quote!(::rand_split::SplitRand::split_rand(#rng)),
+        FieldAttrs::With(path) =>
+            quote!(#path(#rng)),
+        FieldAttrs::Seq => quote!({
+            let ::rand_split::Seq(__value) =
+                ::rand_split::SplitRand::split_rand(#rng);
+            __value
+        }),
+    })
+}
+
+fn expand_co(mut input: DeriveInput) -> Result<TokenStream2> {
+    add_bounds(&mut input, parse_quote!(::rand_split::CoSplitRand));
+
+    let name = &input.ident;
+    let body = match input.data {
+        Data::Struct(ref data) => {
+            let steps = data.fields.iter().enumerate().map(|(i, field)| {
+                let member = match field.ident {
+                    Some(ref ident) => quote!(#ident),
+                    None => {
+                        let index = syn::Index::from(i);
+                        quote!(#index)
+                    }
+                };
+                steer(quote!(&self.#member))
+            });
+            quote! {
+                #(#steps)*
+                __rng
+            }
+        }
+        Data::Enum(ref data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let vname = unraw(ident);
+                let bindings: Vec<Ident> = (0..variant.fields.len())
+                    .map(|i| format_ident!("__field{}", i))
+                    .collect();
+                let pattern = match variant.fields {
+                    Fields::Named(ref fields) => {
+                        let names = fields.named.iter()
+                            .map(|field| field.ident.as_ref().unwrap());
+                        quote!(#name::#ident { #(#names: ref #bindings),* })
+                    }
+                    Fields::Unnamed(_) => quote!(#name::#ident(#(ref #bindings),*)),
+                    Fields::Unit => quote!(#name::#ident),
+                };
+                let steps = bindings.iter().map(|binding| steer(quote!(#binding)));
+                quote! {
+                    #pattern => {
+                        let mut __rng = __rng;
+                        let __rng = ::rand_split::SplitRng::fold_in(&mut __rng, #vname);
+                        #(#steps)*
+                        __rng
+                    }
+                }
+            });
+            quote! {
+                match *self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) =>
+            return Err(Error::new_spanned(
+                &input.ident, "CoSplitRand can't be derived for unions")),
+    };
+
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rand_split::CoSplitRand
+            for #name #ty_generics #where_clause
+        {
+            fn co_split_rand<__R: ::rand_split::SplitRng>(&self, __rng: __R) -> __R {
+                #body
+            }
+        }
+    })
+}
+
+/// Steer `__rng` by the value that `value` refers to.
+fn steer(value: TokenStream2) -> TokenStream2 {
+    quote! {
+        let __rng = ::rand_split::CoSplitRand::co_split_rand(#value, __rng);
+    }
+}
+
+/// Require every type parameter to implement `bound`.
+fn add_bounds(input: &mut DeriveInput, bound: syn::TypeParamBound) {
+    for param in &mut input.generics.params {
+        if let GenericParam::Type(ref mut param) = *param {
+            param.bounds.push(bound.clone());
+        }
+    }
+}
+
+
+/*
+ * Attribute parsing.
+ */
+
+enum FieldAttrs {
+    Default,
+    With(Path),
+    Seq,
+}
+
+fn split_rand_attrs<'a>(attrs: &'a [Attribute])
+                        -> impl Iterator<Item=&'a Attribute> + 'a
+{
+    attrs.iter().filter(|attr| attr.path().is_ident("split_rand"))
+}
+
+fn field_attrs(attrs: &[Attribute]) -> Result<FieldAttrs> {
+    let mut result = FieldAttrs::Default;
+    for attr in split_rand_attrs(attrs) {
+        attr.parse_nested_meta(|meta| {
+            if let FieldAttrs::Default = result {
+            } else {
+                return Err(meta.error(
+                    "a field takes at most one of `with` and `seq`"));
+            }
+            if meta.path.is_ident("with") {
+                let path: LitStr = meta.value()?.parse()?;
+                result = FieldAttrs::With(path.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("seq") {
+                result = FieldAttrs::Seq;
+                Ok(())
+            } else {
+                Err(meta.error("unknown #[split_rand] field attribute"))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+fn variant_weight(attrs: &[Attribute]) -> Result<u64> {
+    let mut result = 1;
+    for attr in split_rand_attrs(attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("weight") {
+                let weight: LitInt = meta.value()?.parse()?;
+                result = weight.base10_parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unknown #[split_rand] variant attribute"))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+/// The name of an identifier as a string literal, without any `r#`.
+fn unraw(ident: &Ident) -> LitStr {
+    LitStr::new(&ident.unraw().to_string(), ident.span())
+}
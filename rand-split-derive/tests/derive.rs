@@ -0,0 +1,185 @@
+extern crate rand_split;
+
+use rand_split::{SplitRng, SplitPrf, SplitRand, CoSplitRand};
+use rand_split::siprng::SipRng;
+
+
+#[derive(SplitRand, Debug, PartialEq)]
+struct Before {
+    x: u64,
+    y: [u32; 4],
+}
+
+// The same fields as `Before`, reordered, plus a new one.
+#[derive(SplitRand, Debug, PartialEq)]
+struct After {
+    z: (u8, bool),
+    y: [u32; 4],
+    x: u64,
+}
+
+#[derive(SplitRand, Debug, PartialEq)]
+struct Pair(u64, u64);
+
+#[derive(SplitRand, Debug, PartialEq)]
+struct Unit;
+
+#[derive(SplitRand, Debug, PartialEq)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+fn odd<R: SplitRng>(rng: &mut R) -> u64 {
+    u64::split_rand(rng) | 1
+}
+
+#[derive(SplitRand, Debug, PartialEq)]
+struct Custom {
+    #[split_rand(with = "odd")]
+    odd: u64,
+    #[split_rand(seq)]
+    seq: u64,
+}
+
+#[allow(dead_code)]
+#[derive(SplitRand, Debug, PartialEq)]
+enum Weighted {
+    #[split_rand(weight = 0)]
+    Never,
+    #[split_rand(weight = 3)]
+    Often { x: u64 },
+    Sometimes(u64),
+}
+
+#[derive(SplitRand, Debug, PartialEq)]
+enum Single {
+    Only { x: u64, y: [u32; 4] },
+}
+
+
+#[test]
+fn test_fields_are_independent() {
+    for i in 0..100 {
+        let before: Before = SipRng::new(i, 0).split_gen();
+        let after: After = SipRng::new(i, 0).split_gen();
+        assert_eq!(before.x, after.x);
+        assert_eq!(before.y, after.y);
+    }
+}
+
+#[test]
+fn test_variant_fields_are_keyed_by_name() {
+    let before: Before = SipRng::new(7, 7).split_gen();
+    let single: Single = SipRng::new(7, 7).split_gen();
+    let Single::Only { x, y } = single;
+    assert!(before.x != x);
+    assert!(before.y != y);
+}
+
+#[test]
+fn test_tuple_struct() {
+    let Pair(a, b) = SipRng::new(1, 2).split_gen();
+    assert!(a != b);
+    assert_eq!(Pair(a, b), SipRng::new(1, 2).split_gen());
+}
+
+#[test]
+fn test_unit_struct() {
+    let unit: Unit = SipRng::new(1, 2).split_gen();
+    assert_eq!(unit, Unit);
+}
+
+#[test]
+fn test_generic_struct() {
+    let a: Wrapper<u64> = SipRng::new(3, 4).split_gen();
+    let b: Wrapper<u64> = SipRng::new(3, 4).split_gen();
+    assert_eq!(a, b);
+}
+
+#[test]
+fn test_field_attributes() {
+    let mut rng = SipRng::new(5, 6);
+    for _ in 0..100 {
+        let custom: Custom = rng.split_gen();
+        assert_eq!(custom.odd & 1, 1);
+    }
+}
+
+#[test]
+fn test_size_is_passed_to_fields() {
+    let mut rng = SipRng::new(10, 11);
+    for size in 0..20 {
+        let wrapper: Wrapper<Vec<u64>> = rng.split_gen_sized(size);
+        assert!(wrapper.inner.len() <= size);
+    }
+}
+
+#[test]
+fn test_variant_weights() {
+    let mut rng = SipRng::new(8, 9);
+    let (mut often, mut sometimes) = (0, 0);
+    for _ in 0..4000 {
+        match rng.split_gen() {
+            Weighted::Never => panic!("generated a variant with weight 0"),
+            Weighted::Often { .. } => often += 1,
+            Weighted::Sometimes(_) => sometimes += 1,
+        }
+    }
+    assert!(often > 2 * sometimes);
+    assert!(sometimes > 0);
+}
+
+
+#[derive(CoSplitRand)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(CoSplitRand)]
+struct Labelled<T>(T, String);
+
+#[allow(dead_code)]
+#[derive(CoSplitRand)]
+enum Expr {
+    Lit(i64),
+    Neg(Box<Expr>),
+    Add { lhs: Box<Expr>, rhs: Box<Expr> },
+    Zero,
+}
+
+fn steer<A: CoSplitRand>(arg: A) -> u64 {
+    arg.co_split_rand(SipRng::new(5, 6).splitn().call(0)).split_gen()
+}
+
+#[test]
+fn test_co_split_rand_struct() {
+    let p = |x, y| steer(Point { x, y });
+    assert_eq!(p(1.0, 2.0), p(1.0, 2.0));
+    assert!(p(1.0, 2.0) != p(2.0, 1.0));
+    assert!(p(0.0, 0.0) != p(-0.0, 0.0));
+    // Fields steer in order, like a tuple.
+    assert_eq!(p(1.0, 2.0), steer((1.0f64, 2.0f64)));
+
+    assert!(steer(Labelled(1u8, "a".to_string()))
+            != steer(Labelled(1u8, "b".to_string())));
+}
+
+#[test]
+fn test_co_split_rand_enum() {
+    use Expr::*;
+    let lit = |n| Box::new(Lit(n));
+    let exprs = [
+        Lit(0), Lit(1), Neg(lit(0)), Neg(lit(1)), Zero,
+        Add { lhs: lit(0), rhs: lit(1) },
+        Add { lhs: lit(1), rhs: lit(0) },
+        Add { lhs: Box::new(Neg(lit(0))), rhs: lit(0) },
+    ];
+    let outputs: Vec<u64> = exprs.iter().map(steer).collect();
+    for (i, a) in outputs.iter().enumerate() {
+        for b in &outputs[i + 1..] {
+            assert!(a != b);
+        }
+    }
+    assert_eq!(steer(Neg(lit(1))), steer(Neg(lit(1))));
+}
@@ -0,0 +1,673 @@
+//! A small property-based testing runner on top of `SplitRand`, in
+//! the style of QuickCheck.
+//!
+//! A property is a function from some `SplitRand` input type to a
+//! test result (`bool`, `()` or a `Result`); panics count as
+//! failures too.  The runner generates inputs with growing size
+//! budgets, and when one fails it **shrinks** it to a simpler input
+//! that still fails, using the input type's `Shrink` impl.
+//!
+//! Every case is generated from a `SipRng` at a known node of a
+//! seeded split tree, so the report for a failure includes a `Case`
+//! that `replay` can use to run that one case again:
+//!
+//! ```
+//! extern crate rand_split;
+//! use rand_split::check::{self, Case, Config};
+//!
+//! # fn main() {
+//! fn prop_reverse_twice(xs: Vec<u32>) -> bool {
+//!     let mut ys = xs.clone();
+//!     ys.reverse();
+//!     ys.reverse();
+//!     xs == ys
+//! }
+//!
+//! // Run with a random seed, panicking with a report on failure.
+//! check::quickcheck(prop_reverse_twice);
+//!
+//! // Run with a fixed seed.
+//! assert!(check::run(&Config::default(), (1, 2), prop_reverse_twice).is_ok());
+//!
+//! // A false property, and a reproducible failing case.
+//! let failure = check::run(&Config::default(), (1, 2), |x: u32| x < 1000)
+//!     .err().unwrap();
+//! assert_eq!(failure.shrunk, 1000);
+//! let case: Case = failure.case;
+//! assert!(check::replay(&case, &Config::default(), |x: u32| x < 1000).is_err());
+//! # }
+//! ```
+
+use rand::Rng;
+use rand::os::OsRng;
+use siprng::SipRng;
+use super::{SplitRand, SplitRng, Step};
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{self, Debug, Display};
+use std::hash::{BuildHasher, Hash};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+
+/// Settings for a property run.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// How many cases to generate.
+    pub tests: u32,
+    /// The size budget of the last case.  Budgets grow linearly
+    /// from 0 for the first case up to this.
+    pub max_size: usize,
+    /// The most shrinking steps to take on a failing input.
+    pub max_shrinks: u32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            tests: 100,
+            max_size: 100,
+            max_shrinks: 1000,
+        }
+    }
+}
+
+/// Everything needed to generate one case's input again.  The input
+/// is generated with a size budget of `size` from the `SipRng` at
+/// `path()` under the root generator for `seed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Case {
+    pub seed: (u64, u64),
+    pub index: u32,
+    pub size: usize,
+}
+
+impl Case {
+    /// The split path from the root generator to this case's.
+    pub fn path(&self) -> [Step; 1] {
+        [Step::Call(self.index)]
+    }
+
+    /// Generate this case's input.
+    pub fn generate<A: SplitRand>(&self) -> A {
+        SipRng::from_seed_and_path(self.seed, &self.path())
+            .split_gen_sized(self.size)
+    }
+}
+
+impl Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "seed = ({:#x}, {:#x}), path = {:?}, size = {}",
+               self.seed.0, self.seed.1, self.path(), self.size)
+    }
+}
+
+/// A failed property, with the input it failed on before and after
+/// shrinking.
+#[derive(Clone, Debug)]
+pub struct Failure<A> {
+    /// The case whose input failed.
+    pub case: Case,
+    /// How many cases passed before this one.
+    pub passed: u32,
+    /// The input as generated.
+    pub original: A,
+    /// The simplest failing input that shrinking found.
+    pub shrunk: A,
+    /// How many shrinking steps it took to get to `shrunk`.
+    pub shrinks: u32,
+    /// What went wrong with `shrunk`.
+    pub message: String,
+}
+
+impl<A: Debug> Display for Failure<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "property failed after {} passed tests", self.passed)?;
+        writeln!(f, "  case: {}", self.case)?;
+        writeln!(f, "  original input: {:?}", self.original)?;
+        writeln!(f, "  shrunk input ({} steps): {:?}", self.shrinks, self.shrunk)?;
+        writeln!(f, "  error: {}", self.message)?;
+        write!(f, "rerun with: check::replay(&{:?}, &config, property)", self.case)
+    }
+}
+
+
+/// The result of running a property once.
+pub trait Testable {
+    fn result(self) -> Result<(), String>;
+}
+
+impl Testable for bool {
+    fn result(self) -> Result<(), String> {
+        if self { Ok(()) } else { Err("property returned false".to_string()) }
+    }
+}
+
+impl Testable for () {
+    fn result(self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<T, E: Debug> Testable for Result<T, E> {
+    fn result(self) -> Result<(), String> {
+        self.map(|_| ()).map_err(|e| format!("{:?}", e))
+    }
+}
+
+
+/// Check a property against `Config::default()` cases, with a
+/// random seed.  Panics with a report if the property fails.
+pub fn quickcheck<A, T, P>(prop: P)
+    where A: SplitRand + Shrink + Debug,
+          T: Testable,
+          P: Fn(A) -> T
+{
+    let mut osrng = OsRng::new().expect("Could not create OsRng");
+    if let Err(failure) = run(&Config::default(), osrng.gen(), prop) {
+        panic!("{}", failure);
+    }
+}
+
+/// Check a property against the cases for `seed`, returning the
+/// first failure, shrunk.
+pub fn run<A, T, P>(config: &Config, seed: (u64, u64), prop: P)
+                    -> Result<(), Failure<A>>
+    where A: SplitRand + Shrink + Debug,
+          T: Testable,
+          P: Fn(A) -> T
+{
+    for index in 0..config.tests {
+        let size = if config.tests <= 1 {
+            config.max_size
+        } else {
+            ((index as u64 * config.max_size as u64)
+             / (config.tests as u64 - 1)) as usize
+        };
+        let case = Case { seed, index, size };
+        run_case(config, &case, index, &prop)?;
+    }
+    Ok(())
+}
+
+/// Run the property on one case again, shrinking the input if it
+/// fails.
+pub fn replay<A, T, P>(case: &Case, config: &Config, prop: P)
+                       -> Result<(), Failure<A>>
+    where A: SplitRand + Shrink + Debug,
+          T: Testable,
+          P: Fn(A) -> T
+{
+    run_case(config, case, 0, &prop)
+}
+
+fn run_case<A, T, P>(config: &Config, case: &Case, passed: u32, prop: &P)
+                     -> Result<(), Failure<A>>
+    where A: SplitRand + Shrink + Debug,
+          T: Testable,
+          P: Fn(A) -> T
+{
+    let original: A = case.generate();
+    let message = match test(prop, original.clone()) {
+        Ok(()) => return Ok(()),
+        Err(message) => message,
+    };
+
+    let (shrunk, shrinks, message) = shrink_failure(config, prop, &original, message);
+    Err(Failure {
+        case: *case,
+        passed,
+        original,
+        shrunk,
+        shrinks,
+        message,
+    })
+}
+
+/// Shrink a failing input greedily: take the first candidate that
+/// still fails and start over from it, until no candidate fails.
+/// Panics while shrinking are expected, so they aren't reported.
+fn shrink_failure<A, T, P>(config: &Config, prop: &P, input: &A, message: String)
+                           -> (A, u32, String)
+    where A: Shrink,
+          T: Testable,
+          P: Fn(A) -> T
+{
+    let _quiet = QuietPanics::new();
+    let (mut current, mut shrinks, mut message) = (input.clone(), 0, message);
+    'shrinking: while shrinks < config.max_shrinks {
+        for candidate in current.shrink() {
+            if let Err(msg) = test(prop, candidate.clone()) {
+                current = candidate;
+                message = msg;
+                shrinks += 1;
+                continue 'shrinking;
+            }
+        }
+        break;
+    }
+    (current, shrinks, message)
+}
+
+thread_local! {
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+static QUIET_HOOK: Once = Once::new();
+
+/// While one of these is alive, panics on the current thread don't
+/// go to the panic hook.  The first one installs a hook that passes
+/// everything else on to the hook that was there before it; other
+/// threads' panics are reported as usual.
+struct QuietPanics;
+
+impl QuietPanics {
+    fn new() -> QuietPanics {
+        QUIET_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                if !QUIET.with(Cell::get) {
+                    previous(info);
+                }
+            }));
+        });
+        QUIET.with(|quiet| quiet.set(true));
+        QuietPanics
+    }
+}
+
+impl Drop for QuietPanics {
+    fn drop(&mut self) {
+        QUIET.with(|quiet| quiet.set(false));
+    }
+}
+
+/// Run a property once, treating a panic as a failure.
+fn test<A, T, P>(prop: &P, input: A) -> Result<(), String>
+    where T: Testable, P: Fn(A) -> T
+{
+    match panic::catch_unwind(AssertUnwindSafe(|| prop(input))) {
+        Ok(result) => result.result(),
+        Err(payload) => {
+            let message = payload.downcast_ref::<String>().cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "property panicked".to_string());
+            Err(format!("panicked: {}", message))
+        }
+    }
+}
+
+
+/// Types whose values can be shrunk to simpler ones.
+pub trait Shrink: Clone {
+    /// Candidates for a simpler version of this value, most
+    /// aggressive first.  The default is to have none, so
+    /// `impl Shrink for MyType {}` is enough to use a type that
+    /// doesn't shrink as a property input.
+    fn shrink(&self) -> Vec<Self> {
+        Vec::new()
+    }
+}
+
+macro_rules! shrink_unsigned_impl {
+    ($t:ident) => {
+        /// Shrinks towards zero.
+        impl Shrink for $t {
+            fn shrink(&self) -> Vec<$t> {
+                let x = *self;
+                let mut result = Vec::new();
+                if x != 0 {
+                    result.push(0);
+                }
+                let mut i = x / 2;
+                while i != 0 {
+                    result.push(x - i);
+                    i /= 2;
+                }
+                result
+            }
+        }
+    }
+}
+
+macro_rules! shrink_signed_impl {
+    ($t:ident) => {
+        /// Shrinks towards zero, trying the positive value first.
+        impl Shrink for $t {
+            fn shrink(&self) -> Vec<$t> {
+                let x = *self;
+                let mut result = Vec::new();
+                if x != 0 {
+                    result.push(0);
+                }
+                if x < 0 {
+                    if let Some(y) = x.checked_neg() {
+                        result.push(y);
+                    }
+                }
+                let mut i = x / 2;
+                while i != 0 {
+                    result.push(x - i);
+                    i /= 2;
+                }
+                result
+            }
+        }
+    }
+}
+
+shrink_unsigned_impl!{usize}
+shrink_unsigned_impl!{u8}
+shrink_unsigned_impl!{u16}
+shrink_unsigned_impl!{u32}
+shrink_unsigned_impl!{u64}
+shrink_unsigned_impl!{u128}
+
+shrink_signed_impl!{isize}
+shrink_signed_impl!{i8}
+shrink_signed_impl!{i16}
+shrink_signed_impl!{i32}
+shrink_signed_impl!{i64}
+shrink_signed_impl!{i128}
+
+macro_rules! shrink_float_impl {
+    ($t:ident) => {
+        /// Shrinks to zero, then to the integer part.
+        impl Shrink for $t {
+            fn shrink(&self) -> Vec<$t> {
+                let x = *self;
+                let mut result = Vec::new();
+                if x != 0.0 && !x.is_nan() {
+                    result.push(0.0);
+                    if x.is_finite() && x.trunc() != x {
+                        result.push(x.trunc());
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+shrink_float_impl!{f32}
+shrink_float_impl!{f64}
+
+impl Shrink for () {}
+
+impl Shrink for bool {
+    fn shrink(&self) -> Vec<bool> {
+        if *self { vec![false] } else { Vec::new() }
+    }
+}
+
+/// Shrinks to `'a'`.
+impl Shrink for char {
+    fn shrink(&self) -> Vec<char> {
+        if *self != 'a' { vec!['a'] } else { Vec::new() }
+    }
+}
+
+impl<T: Shrink> Shrink for Box<T> {
+    fn shrink(&self) -> Vec<Box<T>> {
+        (**self).shrink().into_iter().map(Box::new).collect()
+    }
+}
+
+/// Shrinks to `None`, then shrinks the value inside.
+impl<T: Shrink> Shrink for Option<T> {
+    fn shrink(&self) -> Vec<Option<T>> {
+        match *self {
+            None => Vec::new(),
+            Some(ref x) => {
+                let mut result = vec![None];
+                result.extend(x.shrink().into_iter().map(Some));
+                result
+            }
+        }
+    }
+}
+
+impl<T: Shrink, E: Shrink> Shrink for Result<T, E> {
+    fn shrink(&self) -> Vec<Result<T, E>> {
+        match *self {
+            Ok(ref x) => x.shrink().into_iter().map(Ok).collect(),
+            Err(ref e) => e.shrink().into_iter().map(Err).collect(),
+        }
+    }
+}
+
+/// Removes runs of elements, longest first, then shrinks each
+/// element in place.
+impl<T: Shrink> Shrink for Vec<T> {
+    fn shrink(&self) -> Vec<Vec<T>> {
+        let mut result = Vec::new();
+        let mut k = self.len();
+        while k > 0 {
+            let mut start = 0;
+            while start + k <= self.len() {
+                let mut smaller = self[..start].to_vec();
+                smaller.extend_from_slice(&self[start + k..]);
+                result.push(smaller);
+                start += k;
+            }
+            k /= 2;
+        }
+        for (i, x) in self.iter().enumerate() {
+            for y in x.shrink() {
+                let mut shrunk = self.clone();
+                shrunk[i] = y;
+                result.push(shrunk);
+            }
+        }
+        result
+    }
+}
+
+/// Shrinks like a `Vec<char>`.
+impl Shrink for String {
+    fn shrink(&self) -> Vec<String> {
+        let chars: Vec<char> = self.chars().collect();
+        chars.shrink().into_iter().map(|cs| cs.into_iter().collect()).collect()
+    }
+}
+
+/// Shrinks like a `Vec<(K, V)>`.  Entries whose keys shrink to the
+/// same key are merged.
+impl<K, V, S> Shrink for HashMap<K, V, S>
+    where K: Shrink + Eq + Hash,
+          V: Shrink,
+          S: BuildHasher + Default + Clone
+{
+    fn shrink(&self) -> Vec<HashMap<K, V, S>> {
+        let entries: Vec<(K, V)> = self.iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        entries.shrink().into_iter().map(|es| es.into_iter().collect()).collect()
+    }
+}
+
+/// Shrinks like a `Vec<(K, V)>`.  Entries whose keys shrink to the
+/// same key are merged.
+impl<K: Shrink + Ord, V: Shrink> Shrink for BTreeMap<K, V> {
+    fn shrink(&self) -> Vec<BTreeMap<K, V>> {
+        let entries: Vec<(K, V)> = self.iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        entries.shrink().into_iter().map(|es| es.into_iter().collect()).collect()
+    }
+}
+
+/// Shrinks like a `Vec<T>`.  Elements that shrink to the same value
+/// are merged.
+impl<T, S> Shrink for HashSet<T, S>
+    where T: Shrink + Eq + Hash,
+          S: BuildHasher + Default + Clone
+{
+    fn shrink(&self) -> Vec<HashSet<T, S>> {
+        let elems: Vec<T> = self.iter().cloned().collect();
+        elems.shrink().into_iter().map(|es| es.into_iter().collect()).collect()
+    }
+}
+
+/// Shrinks like a `Vec<T>`.  Elements that shrink to the same value
+/// are merged.
+impl<T: Shrink + Ord> Shrink for BTreeSet<T> {
+    fn shrink(&self) -> Vec<BTreeSet<T>> {
+        let elems: Vec<T> = self.iter().cloned().collect();
+        elems.shrink().into_iter().map(|es| es.into_iter().collect()).collect()
+    }
+}
+
+/// Shrinks one element at a time.
+impl<T: Shrink, const N: usize> Shrink for [T; N] {
+    fn shrink(&self) -> Vec<[T; N]> {
+        let mut result = Vec::new();
+        for (i, x) in self.iter().enumerate() {
+            for y in x.shrink() {
+                let mut shrunk = self.clone();
+                shrunk[i] = y;
+                result.push(shrunk);
+            }
+        }
+        result
+    }
+}
+
+macro_rules! shrink_tuple_impl {
+    ($($tyvar:ident $k:tt),*) => {
+        /// Shrinks one element at a time.
+        impl<$($tyvar: Shrink),*> Shrink for ($($tyvar,)*) {
+            fn shrink(&self) -> Vec<($($tyvar,)*)> {
+                let mut result = Vec::new();
+                $(
+                    for y in self.$k.shrink() {
+                        let mut shrunk = self.clone();
+                        shrunk.$k = y;
+                        result.push(shrunk);
+                    }
+                )*
+                result
+            }
+        }
+    }
+}
+
+shrink_tuple_impl!{A 0}
+shrink_tuple_impl!{A 0, B 1}
+shrink_tuple_impl!{A 0, B 1, C 2}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+                   M 12}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+                   M 12, N 13}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+                   M 12, N 13, O 14}
+shrink_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+                   M 12, N 13, O 14, P 15}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use rand::os::OsRng;
+    use std::collections::{BTreeMap, HashMap};
+    use super::{run, replay, quickcheck, Config, Shrink};
+
+
+    fn gen_seed() -> (u64, u64) {
+        let mut osrng = OsRng::new().expect("Could not create OsRng");
+        osrng.gen()
+    }
+
+    #[test]
+    fn test_passing_property() {
+        quickcheck(|(xs, ys): (Vec<u8>, Vec<u8>)| {
+            let mut zs = xs.clone();
+            zs.extend_from_slice(&ys);
+            zs.len() == xs.len() + ys.len()
+        });
+    }
+
+    #[test]
+    fn test_shrink_integer() {
+        let failure = run(&Config::default(), gen_seed(), |x: u32| x < 1000)
+            .expect_err("property should fail");
+        assert!(failure.original >= 1000);
+        assert_eq!(failure.shrunk, 1000);
+    }
+
+    #[test]
+    fn test_shrink_vec() {
+        let failure = run(&Config::default(), gen_seed(), |xs: Vec<u64>| xs.len() < 3)
+            .expect_err("property should fail");
+        assert_eq!(failure.shrunk, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_panics_are_failures() {
+        let failure = run(&Config::default(), gen_seed(), |x: (i32, bool)| {
+            assert!(x.0 > -10, "too small");
+        }).expect_err("property should fail");
+        assert_eq!(failure.shrunk, (-10, false));
+        assert!(failure.message.contains("too small"));
+    }
+
+    #[test]
+    fn test_replay() {
+        let prop = |xs: Vec<u8>| !xs.contains(&7);
+        let config = Config::default();
+        let failure = run(&config, gen_seed(), prop).expect_err("property should fail");
+        let replayed = replay(&failure.case, &config, prop).expect_err("replay should fail");
+        assert_eq!(failure.original, replayed.original);
+        assert_eq!(failure.shrunk, replayed.shrunk);
+        assert_eq!(failure.shrunk, vec![7]);
+    }
+
+    #[test]
+    fn test_shrink_candidates() {
+        assert_eq!(100u8.shrink(), vec![0, 50, 75, 88, 94, 97, 99]);
+        assert_eq!((-4i8).shrink(), vec![0, 4, -2, -3]);
+        assert_eq!(vec![1u8, 2].shrink(),
+                   vec![vec![], vec![2], vec![1], vec![0, 2], vec![1, 0], vec![1, 1]]);
+        assert!(0u64.shrink().is_empty());
+        assert!(false.shrink().is_empty());
+        assert_eq!((1u128 << 100).shrink()[..2], [0, 1 << 99]);
+        assert_eq!((-4i128).shrink(), vec![0, 4, -2, -3]);
+    }
+
+    #[test]
+    fn test_shrink_map() {
+        let failure = run(&Config::default(), gen_seed(), |m: HashMap<u8, u8>| {
+            m.values().all(|&v| v < 10)
+        }).expect_err("property should fail");
+        assert_eq!(failure.shrunk.into_iter().collect::<Vec<_>>(), vec![(0, 10)]);
+
+        let failure = run(&Config::default(), gen_seed(), |m: BTreeMap<u8, u8>| {
+            m.len() < 2
+        }).expect_err("property should fail");
+        assert_eq!(failure.shrunk.into_iter().collect::<Vec<_>>(), vec![(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn test_shrink_long_tuple() {
+        type T12 = (u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, bool);
+        let failure = run(&Config::default(), gen_seed(), |t: T12| !t.11)
+            .expect_err("property should fail");
+        assert_eq!(failure.shrunk, (0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, true));
+
+        // Too long for `Debug`, so not a property input on its own.
+        let t16 = (0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8,
+                   0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, true);
+        let candidates = t16.shrink();
+        assert_eq!(candidates.len(), 1);
+        assert!(!candidates[0].15);
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compatibility with the `rand_core` 0.6 traits, which `rand` 0.8
+//! and its distributions are built on.  Enabled by the `rand_core`
+//! feature.
+//!
+//! This goes both ways:
+//!
+//! * `SipRng`, `ChaskeyRng`, `ChaskeyLtsRng`, `TwoLcgRng` and
+//!   `generic::Split` implement `RngCore` and `SeedableRng`, so they can drive code
+//!   written against `rand_core`.  Their output is the same as
+//!   through this crate's `Rng` impls, and `SeedableRng` takes the
+//!   same `Seed` as `seed::SeedRng`, with the same `seed_from_u64`.
+//! * `SplitCore<R>` makes a `SplitRng` out of any `rand_core`
+//!   generator `R`, the same way the crate's `Split` does for `rand`
+//!   0.4 generators.
+//!
+//! ```
+//! extern crate rand_core;
+//! extern crate rand_split;
+//!
+//! use rand_core::{RngCore, SeedableRng};
+//! use rand_split::SplitRng;
+//! use rand_split::siprng::SipRng;
+//!
+//! # fn main() {
+//! let mut rng = <SipRng as SeedableRng>::seed_from_u64(7);
+//! let x = RngCore::next_u64(&mut rng);
+//! let xs: Vec<u64> = rng.split_gen();
+//! # }
+//! ```
+//!
+//! Since both `rand::Rng` and `RngCore` have `next_u32`, `next_u64`
+//! and `fill_bytes` methods, code that imports both has to say which
+//! one it means, as above.
+//!
+//! None of this crate's generators implement `CryptoRng`, because
+//! they aren't designed to be secure.  A `Compat<R>` does if `R` does,
+//! but a `SplitCore<R>` doesn't, since it splits with `ChaskeyRng`.
+
+use chaskeyrng::{ChaskeyLtsRng, ChaskeyRng};
+use generic;
+use rand::{self, Rand, Rng};
+use rand_core::{CryptoRng, Error, RngCore, SeedableRng};
+use seed::{Seed, SeedRng};
+use siprng::SipRng;
+use twolcg::TwoLcgRng;
+use super::SplitRng;
+
+
+/// A `SplitRng` over a `rand_core` generator `R`: splitting is done
+/// by a `ChaskeyRng`, and sequential output comes from `R`.
+pub type SplitCore<R> = generic::Split<ChaskeyRng, Compat<R>>;
+
+/// Wraps a `rand_core` generator so that it implements `rand::Rng`
+/// and `rand::Rand`, and so can be the sequential half of a
+/// `generic::Split`.  `Rand` seeds it with `SeedableRng::from_seed`.
+#[derive(Clone, Debug)]
+pub struct Compat<R>(pub R);
+
+impl<R: RngCore> Rng for Compat<R> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+}
+
+impl<R: SeedableRng> Rand for Compat<R> {
+    fn rand<G: Rng>(other: &mut G) -> Compat<R> {
+        let mut seed = R::Seed::default();
+        other.fill_bytes(seed.as_mut());
+        Compat(R::from_seed(seed))
+    }
+}
+
+impl<R: RngCore> RngCore for Compat<R> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl<R: CryptoRng> CryptoRng for Compat<R> {}
+
+
+/// Implements `RngCore` and `SeedableRng` for one of the crate's
+/// generators, in terms of its `rand::Rng` and `SeedRng` impls.
+macro_rules! rand_core_impl {
+    ($t:ty) => {
+        impl RngCore for $t {
+            #[inline]
+            fn next_u32(&mut self) -> u32 {
+                Rng::next_u32(self)
+            }
+
+            #[inline]
+            fn next_u64(&mut self) -> u64 {
+                Rng::next_u64(self)
+            }
+
+            #[inline]
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                Rng::fill_bytes(self, dest)
+            }
+
+            #[inline]
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+                Rng::fill_bytes(self, dest);
+                Ok(())
+            }
+        }
+
+        impl SeedableRng for $t {
+            type Seed = Seed;
+
+            fn from_seed(seed: Seed) -> $t {
+                rand::SeedableRng::from_seed(seed)
+            }
+
+            fn seed_from_u64(n: u64) -> $t {
+                SeedRng::seed_from_u64(n)
+            }
+        }
+    }
+}
+
+rand_core_impl!{SipRng}
+rand_core_impl!{ChaskeyRng}
+rand_core_impl!{ChaskeyLtsRng}
+rand_core_impl!{TwoLcgRng}
+
+impl<S: SplitRng, R: Rng> RngCore for generic::Split<S, R> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        Rng::next_u32(self)
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        Rng::next_u64(self)
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        Rng::fill_bytes(self, dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        Rng::fill_bytes(self, dest);
+        Ok(())
+    }
+}
+
+impl<S, R> SeedableRng for generic::Split<S, R>
+    where S: SplitRng + rand::SeedableRng<Seed>,
+          R: Rng + Rand
+{
+    type Seed = Seed;
+
+    fn from_seed(seed: Seed) -> generic::Split<S, R> {
+        rand::SeedableRng::from_seed(seed)
+    }
+
+    fn seed_from_u64(n: u64) -> generic::Split<S, R> {
+        SeedRng::seed_from_u64(n)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand08::distributions::{Distribution, Uniform};
+    use rand08::rngs::StdRng;
+    use chaskeyrng::ChaskeyLtsRng;
+    use rand_core::{RngCore, SeedableRng};
+    use seed::SeedRng;
+    use siprng::SipRng;
+    use twolcg::TwoLcgRng;
+    use super::SplitCore;
+    use ::SplitRng;
+
+    #[test]
+    fn test_same_output() {
+        let mut ra = <SipRng as SeedableRng>::seed_from_u64(3);
+        let mut rb = <SipRng as SeedRng>::seed_from_u64(3);
+        assert_eq!(RngCore::next_u64(&mut ra), ::rand::Rng::next_u64(&mut rb));
+
+        let mut ra = <ChaskeyLtsRng as SeedableRng>::seed_from_u64(3);
+        let mut rb = <ChaskeyLtsRng as SeedRng>::seed_from_u64(3);
+        assert_eq!(RngCore::next_u64(&mut ra), ::rand::Rng::next_u64(&mut rb));
+
+        let mut ra = <TwoLcgRng as SeedableRng>::from_seed([5; 32]);
+        let mut rb: TwoLcgRng = ::rand::SeedableRng::from_seed([5; 32]);
+        let (mut xs, mut ys) = ([0u8; 13], [0u8; 13]);
+        RngCore::fill_bytes(&mut ra, &mut xs);
+        ::rand::Rng::fill_bytes(&mut rb, &mut ys);
+        assert_eq!(xs, ys);
+    }
+
+    #[test]
+    fn test_distributions() {
+        let mut rng = <SipRng as SeedableRng>::seed_from_u64(11);
+        let dice = Uniform::new_inclusive(1, 6);
+        for _ in 0..100 {
+            let roll: u32 = dice.sample(&mut rng);
+            assert!((1..=6).contains(&roll));
+        }
+
+        // The generator is still splittable after driving `rand` 0.8.
+        let mut child = rng.split();
+        let roll: u32 = dice.sample(&mut child);
+        assert!((1..=6).contains(&roll));
+    }
+
+    fn gen_split_core() -> SplitCore<StdRng> {
+        SeedRng::seed_from_u64(17)
+    }
+
+    #[test]
+    fn test_split_core() {
+        ::tests::test_split_rand_independence(&mut gen_split_core());
+        ::tests::test_split_rand_split(&mut gen_split_core());
+        ::tests::test_prf_index_domains(&mut gen_split_core());
+        ::tests::test_fold_in(&mut gen_split_core());
+        ::tests::test_at_path(gen_split_core);
+    }
+}
@@ -0,0 +1,452 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Choosing the generator algorithm at runtime.
+//!
+//! `SplitRng` can't be made into a trait object, because it's
+//! `Sized` and has an associated PRF type.  This module offers two
+//! ways around that:
+//!
+//! * `DynSplitRng` and `DynSplitPrf`, object-safe versions of the two
+//!   traits that every `SplitRng` implements, whose operations return
+//!   boxed trait objects.  `Box<dyn DynSplitRng>` is itself a
+//!   `SplitRng`.  These need the `alloc` feature, and the `SplitRng`
+//!   impl needs `std`.
+//! * `AnySplitRng`, an enum over this crate's generators, picked by an
+//!   `Algorithm` that parses from a name.
+//!
+//! ```
+//! use rand_split::SplitRng;
+//! use rand_split::dynamic::{Algorithm, AnySplitRng, DynSplitRng};
+//! use rand_split::seed::expand_u64;
+//!
+//! let algorithm: Algorithm = "chaskey".parse().unwrap();
+//! let mut rng = AnySplitRng::new(algorithm, expand_u64(42));
+//! let xs: Vec<u64> = rng.split_gen_sized(10);
+//!
+//! let mut boxed: Box<dyn DynSplitRng> = Box::new(rng);
+//! let ys: Vec<u64> = boxed.split_gen_sized(10);
+//! ```
+//!
+//! The algorithm names are `siprng`, `chaskey` and `twolcg`, and
+//! `split/chaskey` for the crate's `Split<XorShiftRng>`; they agree
+//! with the names that `token` uses where both have one.
+
+use chaskeyrng::{ChaskeyRng, ChaskeyPrf};
+use rand::{Rng, SeedableRng, XorShiftRng};
+use seed::Seed;
+#[cfg(feature = "std")]
+use seed::SeedRng;
+use siprng::{SipRng, SipPrf};
+use core::fmt::{self, Display};
+use core::hash::Hash;
+#[cfg(feature = "alloc")]
+use core::hash::Hasher;
+use core::str::FromStr;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io;
+use twolcg::{TwoLcgRng, TwoLcgPrf};
+use super::{SplitRng, SplitPrf, Split, Prf};
+
+
+/// An object-safe version of `SplitRng`, implemented by every
+/// `SplitRng`.  Splitting returns boxed trait objects.
+#[cfg(feature = "alloc")]
+pub trait DynSplitRng: Rng {
+    /// Split a pseudo-random function off this generator.
+    fn splitn_dyn(&mut self) -> Box<dyn DynSplitPrf>;
+
+    /// Split a second RNG off this one.
+    fn split_dyn(&mut self) -> Box<dyn DynSplitRng>;
+}
+
+/// An object-safe version of `SplitPrf`, implemented by the PRFs of
+/// every `SplitRng`.
+#[cfg(feature = "alloc")]
+pub trait DynSplitPrf {
+    /// Construct the generator at a `u32` index.
+    fn call_dyn(&self, i: u32) -> Box<dyn DynSplitRng>;
+
+    /// Construct the generator at a `u64` index.
+    fn call_u64_dyn(&self, i: u64) -> Box<dyn DynSplitRng>;
+
+    /// Construct the generator at a `u128` index.
+    fn call_u128_dyn(&self, i: u128) -> Box<dyn DynSplitRng>;
+
+    /// Construct the generator for some `Hash` data.  This gives the
+    /// same generator as `SplitPrf::fold_in` on the same data.
+    fn fold_in_dyn(&self, data: &dyn DynHash) -> Box<dyn DynSplitRng>;
+}
+
+/// An object-safe version of `Hash`, so that `DynSplitPrf` can take
+/// data to fold in.  Every `Hash` type implements it.
+#[cfg(feature = "alloc")]
+pub trait DynHash {
+    /// Feed this value into the given hasher.
+    fn dyn_hash(&self, state: &mut dyn Hasher);
+}
+
+#[cfg(feature = "alloc")]
+impl<H: Hash + ?Sized> DynHash for H {
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        self.hash(&mut state);
+    }
+}
+
+/// Lets a `&dyn DynHash` be passed where a `Hash` is expected.
+#[cfg(feature = "alloc")]
+struct DynHashed<'a>(&'a dyn DynHash);
+
+#[cfg(feature = "alloc")]
+impl<'a> Hash for DynHashed<'a> {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.0.dyn_hash(state);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<R: SplitRng + 'static> DynSplitRng for R where R::Prf: 'static {
+    fn splitn_dyn(&mut self) -> Box<dyn DynSplitPrf> {
+        Box::new(PrfBox::<R> { prf: self.splitn() })
+    }
+
+    fn split_dyn(&mut self) -> Box<dyn DynSplitRng> {
+        Box::new(self.split())
+    }
+}
+
+/// A `SplitRng`'s PRF, tagged with the generator type so that
+/// `DynSplitPrf` can be implemented for it.
+#[cfg(feature = "alloc")]
+struct PrfBox<R: SplitRng> {
+    prf: R::Prf,
+}
+
+#[cfg(feature = "alloc")]
+impl<R: SplitRng + 'static> DynSplitPrf for PrfBox<R> {
+    fn call_dyn(&self, i: u32) -> Box<dyn DynSplitRng> {
+        Box::new(self.prf.call(i))
+    }
+
+    fn call_u64_dyn(&self, i: u64) -> Box<dyn DynSplitRng> {
+        Box::new(self.prf.call_u64(i))
+    }
+
+    fn call_u128_dyn(&self, i: u128) -> Box<dyn DynSplitRng> {
+        Box::new(self.prf.call_u128(i))
+    }
+
+    fn fold_in_dyn(&self, data: &dyn DynHash) -> Box<dyn DynSplitRng> {
+        Box::new(self.prf.fold_in(&DynHashed(data)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl SplitRng for Box<dyn DynSplitRng> {
+    type Prf = Box<dyn DynSplitPrf>;
+
+    fn splitn(&mut self) -> Box<dyn DynSplitPrf> {
+        (**self).splitn_dyn()
+    }
+
+    fn split(&mut self) -> Box<dyn DynSplitRng> {
+        (**self).split_dyn()
+    }
+}
+
+#[cfg(feature = "std")]
+impl SplitPrf<Box<dyn DynSplitRng>> for Box<dyn DynSplitPrf> {
+    fn call(&self, i: u32) -> Box<dyn DynSplitRng> {
+        (**self).call_dyn(i)
+    }
+
+    fn call_u64(&self, i: u64) -> Box<dyn DynSplitRng> {
+        (**self).call_u64_dyn(i)
+    }
+
+    fn call_u128(&self, i: u128) -> Box<dyn DynSplitRng> {
+        (**self).call_u128_dyn(i)
+    }
+
+    fn fold_in<H: Hash + ?Sized>(&self, data: &H) -> Box<dyn DynSplitRng> {
+        (**self).fold_in_dyn(&data)
+    }
+}
+
+
+/// The generator algorithms that `AnySplitRng` can run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// `siprng::SipRng`, named `siprng`.
+    SipRng,
+    /// `chaskeyrng::ChaskeyRng`, named `chaskey`.
+    Chaskey,
+    /// `twolcg::TwoLcgRng`, named `twolcg`.
+    TwoLcg,
+    /// The crate's `Split<XorShiftRng>`, named `split/chaskey`.
+    Split,
+}
+
+impl Algorithm {
+    /// Every algorithm, in declaration order.
+    pub const ALL: [Algorithm; 4] =
+        [Algorithm::SipRng, Algorithm::Chaskey, Algorithm::TwoLcg, Algorithm::Split];
+
+    /// The algorithm's name, which `parse` accepts.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Algorithm::SipRng => "siprng",
+            Algorithm::Chaskey => "chaskey",
+            Algorithm::TwoLcg => "twolcg",
+            Algorithm::Split => "split/chaskey",
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = UnknownAlgorithm;
+
+    fn from_str(s: &str) -> Result<Algorithm, UnknownAlgorithm> {
+        Algorithm::ALL.iter()
+            .find(|algorithm| algorithm.name() == s)
+            .cloned()
+            .ok_or(UnknownAlgorithm)
+    }
+}
+
+/// The error for parsing a name that isn't an `Algorithm`'s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownAlgorithm;
+
+impl Display for UnknownAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("unknown generator algorithm")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for UnknownAlgorithm {}
+
+
+/// A splittable generator whose algorithm is chosen at runtime.  Each
+/// variant behaves exactly like the generator it holds.
+pub enum AnySplitRng {
+    /// A `SipRng`, for `Algorithm::SipRng`.
+    SipRng(SipRng),
+    /// A `ChaskeyRng`, for `Algorithm::Chaskey`.
+    Chaskey(ChaskeyRng),
+    /// A `TwoLcgRng`, for `Algorithm::TwoLcg`.
+    TwoLcg(TwoLcgRng),
+    /// A `Split<XorShiftRng>`, for `Algorithm::Split`.
+    Split(Split<XorShiftRng>),
+}
+
+/// The PRF type that corresponds to `AnySplitRng`.
+pub enum AnyPrf {
+    SipRng(SipPrf),
+    Chaskey(ChaskeyPrf),
+    TwoLcg(TwoLcgPrf),
+    Split(Prf<XorShiftRng>),
+}
+
+/// Apply the same expression to whichever generator or PRF an
+/// `AnySplitRng` or `AnyPrf` holds.
+macro_rules! dispatch {
+    ($any:ident, $value:expr, $x:ident => $body:expr) => {
+        match $value {
+            $any::SipRng($x) => $body,
+            $any::Chaskey($x) => $body,
+            $any::TwoLcg($x) => $body,
+            $any::Split($x) => $body,
+        }
+    }
+}
+
+/// Like `dispatch!`, but wrap the result back up in the same variant.
+macro_rules! dispatch_wrap {
+    ($any:ident, $wrap:ident, $value:expr, $x:ident => $body:expr) => {
+        match $value {
+            $any::SipRng($x) => $wrap::SipRng($body),
+            $any::Chaskey($x) => $wrap::Chaskey($body),
+            $any::TwoLcg($x) => $wrap::TwoLcg($body),
+            $any::Split($x) => $wrap::Split($body),
+        }
+    }
+}
+
+impl AnySplitRng {
+    /// The generator for `algorithm` with the given seed, the same as
+    /// that generator's `SeedableRng::from_seed`.
+    pub fn new(algorithm: Algorithm, seed: Seed) -> AnySplitRng {
+        match algorithm {
+            Algorithm::SipRng => AnySplitRng::SipRng(SeedableRng::from_seed(seed)),
+            Algorithm::Chaskey => AnySplitRng::Chaskey(SeedableRng::from_seed(seed)),
+            Algorithm::TwoLcg => AnySplitRng::TwoLcg(SeedableRng::from_seed(seed)),
+            Algorithm::Split => AnySplitRng::Split(SeedableRng::from_seed(seed)),
+        }
+    }
+
+    /// The generator for `algorithm` seeded from the operating
+    /// system's entropy source.
+    #[cfg(feature = "std")]
+    pub fn from_entropy(algorithm: Algorithm) -> io::Result<AnySplitRng> {
+        Ok(match algorithm {
+            Algorithm::SipRng => AnySplitRng::SipRng(SeedRng::from_entropy()?),
+            Algorithm::Chaskey => AnySplitRng::Chaskey(SeedRng::from_entropy()?),
+            Algorithm::TwoLcg => AnySplitRng::TwoLcg(SeedRng::from_entropy()?),
+            Algorithm::Split => AnySplitRng::Split(SeedRng::from_entropy()?),
+        })
+    }
+
+    /// The algorithm this generator runs.
+    pub fn algorithm(&self) -> Algorithm {
+        match *self {
+            AnySplitRng::SipRng(_) => Algorithm::SipRng,
+            AnySplitRng::Chaskey(_) => Algorithm::Chaskey,
+            AnySplitRng::TwoLcg(_) => Algorithm::TwoLcg,
+            AnySplitRng::Split(_) => Algorithm::Split,
+        }
+    }
+}
+
+impl Rng for AnySplitRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        dispatch!(AnySplitRng, self, rng => rng.next_u32())
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        dispatch!(AnySplitRng, self, rng => rng.next_u64())
+    }
+
+    #[inline]
+    fn next_f32(&mut self) -> f32 {
+        dispatch!(AnySplitRng, self, rng => rng.next_f32())
+    }
+
+    #[inline]
+    fn next_f64(&mut self) -> f64 {
+        dispatch!(AnySplitRng, self, rng => rng.next_f64())
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dispatch!(AnySplitRng, self, rng => rng.fill_bytes(dest))
+    }
+}
+
+impl SplitRng for AnySplitRng {
+    type Prf = AnyPrf;
+
+    fn splitn(&mut self) -> AnyPrf {
+        dispatch_wrap!(AnySplitRng, AnyPrf, self, rng => rng.splitn())
+    }
+
+    fn split(&mut self) -> AnySplitRng {
+        dispatch_wrap!(AnySplitRng, AnySplitRng, self, rng => rng.split())
+    }
+}
+
+impl SplitPrf<AnySplitRng> for AnyPrf {
+    fn call(&self, i: u32) -> AnySplitRng {
+        dispatch_wrap!(AnyPrf, AnySplitRng, self, prf => prf.call(i))
+    }
+
+    fn call_u64(&self, i: u64) -> AnySplitRng {
+        dispatch_wrap!(AnyPrf, AnySplitRng, self, prf => prf.call_u64(i))
+    }
+
+    fn call_u128(&self, i: u128) -> AnySplitRng {
+        dispatch_wrap!(AnyPrf, AnySplitRng, self, prf => prf.call_u128(i))
+    }
+
+    fn fold_in<H: Hash + ?Sized>(&self, data: &H) -> AnySplitRng {
+        dispatch_wrap!(AnyPrf, AnySplitRng, self, prf => prf.fold_in(data))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::os::OsRng;
+    use seed::Seed;
+    use siprng::SipRng;
+    use super::{Algorithm, AnySplitRng, DynSplitRng, UnknownAlgorithm};
+    use ::{SplitRng, SplitPrf, Step};
+
+    fn gen_seed() -> Seed {
+        let mut osrng = OsRng::new().expect("Could not create OsRng");
+        osrng.gen()
+    }
+
+    #[test]
+    fn test_parse_algorithm() {
+        for &algorithm in Algorithm::ALL.iter() {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+        assert_eq!("siprng".parse(), Ok(Algorithm::SipRng));
+        assert_eq!("chaskey".parse(), Ok(Algorithm::Chaskey));
+        assert_eq!("SipRng".parse::<Algorithm>(), Err(UnknownAlgorithm));
+    }
+
+    #[test]
+    fn test_any_split_rng() {
+        for &algorithm in Algorithm::ALL.iter() {
+            let seed = gen_seed();
+            let mut rng = AnySplitRng::new(algorithm, seed);
+            assert_eq!(rng.algorithm(), algorithm);
+            ::tests::test_split_rand_independence(&mut rng);
+            ::tests::test_prf_index_domains(&mut rng);
+            ::tests::test_fold_in(&mut rng);
+            ::tests::test_split_rand_split(&mut rng);
+            ::tests::test_at_path(|| AnySplitRng::new(algorithm, seed));
+        }
+    }
+
+    #[test]
+    fn test_any_matches_inner() {
+        let seed = gen_seed();
+        let path = [Step::Right, Step::CallU64(9), Step::Left, Step::Call(2)];
+        let mut ra = AnySplitRng::new(Algorithm::SipRng, seed).at_path(&path);
+        let mut rb = SipRng::from_seed(seed).at_path(&path);
+        assert_eq!(ra.gen::<[u64; 8]>(), rb.gen::<[u64; 8]>());
+        assert_eq!(ra.splitn().fold_in("x").gen::<u64>(),
+                   rb.splitn().fold_in("x").gen::<u64>());
+    }
+
+    #[test]
+    fn test_dyn_split_rng() {
+        let seed = gen_seed();
+        let mk = || -> Box<dyn DynSplitRng> { Box::new(SipRng::from_seed(seed)) };
+        ::tests::test_split_rand_independence(&mut mk());
+        ::tests::test_prf_index_domains(&mut mk());
+        ::tests::test_fold_in(&mut mk());
+        ::tests::test_at_path(mk);
+
+        // Boxing doesn't change any output, even of `fold_in`.
+        let path = [Step::Call(4), Step::Right, Step::CallU128(1 << 100)];
+        let mut ra = mk().at_path(&path);
+        let mut rb = SipRng::from_seed(seed).at_path(&path);
+        assert_eq!(ra.gen::<[u64; 8]>(), rb.gen::<[u64; 8]>());
+        assert_eq!(ra.fold_in(&(1u8, "two", [3u64])).gen::<[u64; 4]>(),
+                   rb.fold_in(&(1u8, "two", [3u64])).gen::<[u64; 4]>());
+    }
+}
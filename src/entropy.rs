@@ -0,0 +1,155 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A `SplitRng` that draws fresh entropy from the operating system
+//! on every split, for running code written against `SplitRng` with
+//! real randomness instead of a reproducible seed.
+//!
+//! ```
+//! use rand_split::SplitRng;
+//! use rand_split::entropy::EntropySplitRng;
+//! use rand_split::seed::SeedRng;
+//! use rand_split::siprng::SipRng;
+//!
+//! fn simulate<R: SplitRng>(rng: &mut R) -> Vec<u32> {
+//!     rng.split_gen()
+//! }
+//!
+//! let reproducible = simulate(&mut SipRng::seed_from_u64(1));
+//! assert_eq!(reproducible, simulate(&mut SipRng::seed_from_u64(1)));
+//!
+//! let mut rng: EntropySplitRng = EntropySplitRng::new().expect("no entropy source");
+//! let production = simulate(&mut rng);
+//! ```
+//!
+//! Nothing an `EntropySplitRng` produces can be reproduced: its
+//! splits and its PRFs' results are seeded afresh each time, so a PRF
+//! called twice with the same argument gives two unrelated
+//! generators.  Code that relies on that kind of reproducibility,
+//! like generated functions, won't behave the same way with it.
+
+use rand::Rng;
+use seed::{self, SeedRng};
+use siprng::SipRng;
+use std::hash::Hash;
+use std::io;
+use std::marker::PhantomData;
+use super::{SplitRng, SplitPrf};
+
+
+/// A `SplitRng` whose every split and PRF call is seeded from the
+/// operating system's entropy source.  Sequential output between
+/// splits comes from a generator of type `R`.
+///
+/// The constructor reports failure to reach the entropy source as an
+/// error.  Should it fail after that, splitting panics, the same way
+/// `OsRng`'s own methods do.
+pub struct EntropySplitRng<R = SipRng> {
+    rng: R,
+}
+
+/// The PRF type that corresponds to `EntropySplitRng`.  It ignores
+/// its arguments.
+pub struct EntropyPrf<R = SipRng> {
+    phantom: PhantomData<R>,
+}
+
+impl<R: SeedRng> EntropySplitRng<R> {
+    /// A generator seeded from the operating system's entropy
+    /// source.
+    pub fn new() -> io::Result<EntropySplitRng<R>> {
+        Ok(EntropySplitRng { rng: R::from_entropy()? })
+    }
+
+    fn fresh() -> EntropySplitRng<R> {
+        let seed = seed::entropy_seed().expect("could not read OS entropy");
+        EntropySplitRng { rng: R::from_seed(seed) }
+    }
+}
+
+impl<R: SeedRng> Rng for EntropySplitRng<R> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+}
+
+impl<R: SeedRng> SplitRng for EntropySplitRng<R> {
+    type Prf = EntropyPrf<R>;
+
+    fn split(&mut self) -> EntropySplitRng<R> {
+        EntropySplitRng::fresh()
+    }
+
+    fn splitn(&mut self) -> EntropyPrf<R> {
+        EntropyPrf { phantom: PhantomData }
+    }
+}
+
+impl<R: SeedRng> SplitPrf<EntropySplitRng<R>> for EntropyPrf<R> {
+    fn call(&self, _i: u32) -> EntropySplitRng<R> {
+        EntropySplitRng::fresh()
+    }
+
+    fn call_u64(&self, _i: u64) -> EntropySplitRng<R> {
+        EntropySplitRng::fresh()
+    }
+
+    fn call_u128(&self, _i: u128) -> EntropySplitRng<R> {
+        EntropySplitRng::fresh()
+    }
+
+    fn fold_in<H: Hash + ?Sized>(&self, _data: &H) -> EntropySplitRng<R> {
+        EntropySplitRng::fresh()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chaskeyrng::ChaskeyRng;
+    use rand::Rng;
+    use seed::SeedRng;
+    use siprng::SipRng;
+    use super::EntropySplitRng;
+    use ::{SplitRng, SplitPrf};
+
+    #[test]
+    fn test_from_entropy() {
+        let mut ra = SipRng::from_entropy().expect("from_entropy");
+        let mut rb = SipRng::from_entropy().expect("from_entropy");
+        assert!(ra.gen::<[u64; 4]>() != rb.gen::<[u64; 4]>());
+    }
+
+    #[test]
+    fn test_entropy_splits() {
+        let mut rng: EntropySplitRng<ChaskeyRng> = EntropySplitRng::new().expect("new");
+        let mut ra = rng.split();
+        let mut rb = rng.split();
+        assert!(ra.gen::<[u64; 4]>() != rb.gen::<[u64; 4]>());
+
+        let prf = rng.splitn();
+        let mut rc = prf.call(0);
+        let mut rd = prf.call(0);
+        assert!(rc.gen::<[u64; 4]>() != rd.gen::<[u64; 4]>());
+
+        let xs: Vec<u64> = rng.split_gen_sized(10);
+        assert!(xs.len() <= 10);
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A C interface to `SipRng` and `ChaskeyRng`, so that programs in
+//! other languages can consume the same splittable streams.  Enabled
+//! by the `ffi` feature.
+//!
+//! The C declarations are in `include/rand_split.h`.  Generators and
+//! PRFs are opaque handles that the caller owns: every function that
+//! returns one allocates it, and it must be released with the
+//! matching `_free` function.  Build a library to link against with:
+//!
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type staticlib
+//! ```
+//!
+//! For `SipRng` the functions are:
+//!
+//! * `rand_split_siprng_new(seed)`: the generator for a 32-byte
+//!   `Seed`, as `SeedableRng::from_seed` gives it;
+//! * `rand_split_siprng_next_u64(rng)` and
+//!   `rand_split_siprng_fill_bytes(rng, dest, len)`;
+//! * `rand_split_siprng_split(rng)` and
+//!   `rand_split_siprng_splitn(rng)`;
+//! * `rand_split_sipprf_call(prf, i)` and
+//!   `rand_split_sipprf_call_u64(prf, i)`;
+//! * `rand_split_siprng_clone(rng)`, `rand_split_siprng_free(rng)`
+//!   and `rand_split_sipprf_free(prf)`.
+//!
+//! The `ChaskeyRng` functions are the same with `chaskeyrng` and
+//! `chaskeyprf` in their names.
+//!
+//! Passing a null pointer to a `_free` function does nothing, and
+//! `_new` returns null if `seed` is null.  Every other pointer must be
+//! a live handle of the right type, and `dest` must point to `len`
+//! writable bytes.
+
+use chaskeyrng::{ChaskeyRng, ChaskeyPrf};
+use rand::{Rng, SeedableRng};
+use seed::Seed;
+use siprng::{SipRng, SipPrf};
+use std::ptr;
+use std::slice;
+use super::{SplitRng, SplitPrf};
+
+
+/// Defines the C functions for one generator type and its PRF type.
+macro_rules! ffi_impl {
+    ($rng:ty, $prf:ty,
+     $new:ident, $next_u64:ident, $fill_bytes:ident,
+     $split:ident, $splitn:ident, $call:ident, $call_u64:ident,
+     $clone:ident, $free:ident, $prf_free:ident) => {
+        /// Create a generator from a pointer to a 32-byte seed, or
+        /// return null if the pointer is null.
+        ///
+        /// # Safety
+        ///
+        /// `seed` must be null or point to 32 readable bytes.
+        #[no_mangle]
+        pub unsafe extern "C" fn $new(seed: *const Seed) -> *mut $rng {
+            if seed.is_null() {
+                return ptr::null_mut();
+            }
+            Box::into_raw(Box::new(<$rng as SeedableRng<Seed>>::from_seed(*seed)))
+        }
+
+        /// The generator's next `u64`.
+        ///
+        /// # Safety
+        ///
+        /// `rng` must be a live generator handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $next_u64(rng: *mut $rng) -> u64 {
+            (*rng).next_u64()
+        }
+
+        /// Fill `len` bytes at `dest` with the generator's output.
+        ///
+        /// # Safety
+        ///
+        /// `rng` must be a live generator handle, and `dest` must point
+        /// to `len` writable bytes.
+        #[no_mangle]
+        pub unsafe extern "C" fn $fill_bytes(rng: *mut $rng, dest: *mut u8, len: usize) {
+            if len > 0 {
+                (*rng).fill_bytes(slice::from_raw_parts_mut(dest, len));
+            }
+        }
+
+        /// Split a new generator off this one.
+        ///
+        /// # Safety
+        ///
+        /// `rng` must be a live generator handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $split(rng: *mut $rng) -> *mut $rng {
+            Box::into_raw(Box::new((*rng).split()))
+        }
+
+        /// Split a PRF off this generator.
+        ///
+        /// # Safety
+        ///
+        /// `rng` must be a live generator handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $splitn(rng: *mut $rng) -> *mut $prf {
+            Box::into_raw(Box::new((*rng).splitn()))
+        }
+
+        /// The PRF's generator at a `u32` index.
+        ///
+        /// # Safety
+        ///
+        /// `prf` must be a live PRF handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $call(prf: *const $prf, i: u32) -> *mut $rng {
+            Box::into_raw(Box::new((*prf).call(i)))
+        }
+
+        /// The PRF's generator at a `u64` index.
+        ///
+        /// # Safety
+        ///
+        /// `prf` must be a live PRF handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $call_u64(prf: *const $prf, i: u64) -> *mut $rng {
+            Box::into_raw(Box::new((*prf).call_u64(i)))
+        }
+
+        /// A copy of the generator, in the same state.
+        ///
+        /// # Safety
+        ///
+        /// `rng` must be a live generator handle.
+        #[no_mangle]
+        pub unsafe extern "C" fn $clone(rng: *const $rng) -> *mut $rng {
+            Box::into_raw(Box::new((*rng).clone()))
+        }
+
+        /// Release a generator.
+        ///
+        /// # Safety
+        ///
+        /// `rng` must be null or a live generator handle, which is not
+        /// used again.
+        #[no_mangle]
+        pub unsafe extern "C" fn $free(rng: *mut $rng) {
+            if !rng.is_null() {
+                drop(Box::from_raw(rng));
+            }
+        }
+
+        /// Release a PRF.
+        ///
+        /// # Safety
+        ///
+        /// `prf` must be null or a live PRF handle, which is not used
+        /// again.
+        #[no_mangle]
+        pub unsafe extern "C" fn $prf_free(prf: *mut $prf) {
+            if !prf.is_null() {
+                drop(Box::from_raw(prf));
+            }
+        }
+    }
+}
+
+ffi_impl!{SipRng, SipPrf,
+          rand_split_siprng_new, rand_split_siprng_next_u64,
+          rand_split_siprng_fill_bytes, rand_split_siprng_split,
+          rand_split_siprng_splitn, rand_split_sipprf_call,
+          rand_split_sipprf_call_u64, rand_split_siprng_clone,
+          rand_split_siprng_free, rand_split_sipprf_free}
+
+ffi_impl!{ChaskeyRng, ChaskeyPrf,
+          rand_split_chaskeyrng_new, rand_split_chaskeyrng_next_u64,
+          rand_split_chaskeyrng_fill_bytes, rand_split_chaskeyrng_split,
+          rand_split_chaskeyrng_splitn, rand_split_chaskeyprf_call,
+          rand_split_chaskeyprf_call_u64, rand_split_chaskeyrng_clone,
+          rand_split_chaskeyrng_free, rand_split_chaskeyprf_free}
+
+
+#[cfg(test)]
+mod tests {
+    use chaskeyrng::ChaskeyRng;
+    use rand::{Rng, SeedableRng};
+    use seed::Seed;
+    use siprng::SipRng;
+    use std::ptr;
+    use super::*;
+    use ::{SplitRng, SplitPrf};
+
+    /// The seed that `tests/ffi/test_ffi.c` uses: bytes 0 to 31.
+    fn test_seed() -> Seed {
+        let mut seed = [0u8; 32];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        seed
+    }
+
+    #[test]
+    fn test_siprng_ffi() {
+        let seed = test_seed();
+        let mut expected: SipRng = SeedableRng::from_seed(seed);
+        unsafe {
+            assert!(rand_split_siprng_new(ptr::null()).is_null());
+            let rng = rand_split_siprng_new(&seed);
+            assert_eq!(rand_split_siprng_next_u64(rng), expected.next_u64());
+
+            let mut bytes = [0u8; 11];
+            let mut expected_bytes = [0u8; 11];
+            rand_split_siprng_fill_bytes(rng, bytes.as_mut_ptr(), bytes.len());
+            expected.fill_bytes(&mut expected_bytes);
+            assert_eq!(bytes, expected_bytes);
+
+            let copy = rand_split_siprng_clone(rng);
+            let child = rand_split_siprng_split(rng);
+            assert_eq!(rand_split_siprng_next_u64(child), expected.split().next_u64());
+            assert_eq!(rand_split_siprng_next_u64(rng), expected.next_u64());
+            assert_eq!(rand_split_siprng_next_u64(copy), {
+                let mut r: SipRng = SeedableRng::from_seed(seed);
+                r.next_u64();
+                r.fill_bytes(&mut [0u8; 11]);
+                r.next_u64()
+            });
+
+            let prf = rand_split_siprng_splitn(rng);
+            let expected_prf = expected.splitn();
+            let at7 = rand_split_sipprf_call(prf, 7);
+            let at7_u64 = rand_split_sipprf_call_u64(prf, 7);
+            assert_eq!(rand_split_siprng_next_u64(at7), expected_prf.call(7).next_u64());
+            assert_eq!(rand_split_siprng_next_u64(at7_u64),
+                       expected_prf.call_u64(7).next_u64());
+
+            for r in &[rng, copy, child, at7, at7_u64] {
+                rand_split_siprng_free(*r);
+            }
+            rand_split_sipprf_free(prf);
+            rand_split_siprng_free(ptr::null_mut());
+            rand_split_sipprf_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_chaskeyrng_ffi() {
+        let seed = test_seed();
+        let mut expected: ChaskeyRng = SeedableRng::from_seed(seed);
+        unsafe {
+            let rng = rand_split_chaskeyrng_new(&seed);
+            assert_eq!(rand_split_chaskeyrng_next_u64(rng), expected.next_u64());
+
+            let child = rand_split_chaskeyrng_split(rng);
+            assert_eq!(rand_split_chaskeyrng_next_u64(child),
+                       expected.split().next_u64());
+
+            let prf = rand_split_chaskeyrng_splitn(rng);
+            let at3 = rand_split_chaskeyprf_call(prf, 3);
+            assert_eq!(rand_split_chaskeyrng_next_u64(at3),
+                       expected.splitn().call(3).next_u64());
+
+            for r in &[rng, child, at3] {
+                rand_split_chaskeyrng_free(*r);
+            }
+            rand_split_chaskeyprf_free(prf);
+        }
+    }
+
+    /// The values that `tests/ffi/test_ffi.c` expects, computed with
+    /// the Rust API.  Keep the two in sync.
+    #[test]
+    fn test_c_known_answers() {
+        let seed = test_seed();
+
+        let mut rng: SipRng = SeedableRng::from_seed(seed);
+        let first = rng.next_u64();
+        let split = rng.split().next_u64();
+        let call = rng.splitn().call(7).next_u64();
+        assert_eq!([first, split, call], SIPRNG_KNOWN_ANSWERS);
+
+        let mut rng: ChaskeyRng = SeedableRng::from_seed(seed);
+        let first = rng.next_u64();
+        let split = rng.split().next_u64();
+        let call = rng.splitn().call(7).next_u64();
+        assert_eq!([first, split, call], CHASKEYRNG_KNOWN_ANSWERS);
+    }
+
+    const SIPRNG_KNOWN_ANSWERS: [u64; 3] =
+        [0xb6ef6ba3af59e55d, 0x6dbe39b14105e60a, 0x8bbe40357f8d2be3];
+    const CHASKEYRNG_KNOWN_ANSWERS: [u64; 3] =
+        [0xa31305a4dc61ebd3, 0x6da07d1514d297ae, 0xc4a49cd20852db0f];
+}
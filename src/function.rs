@@ -0,0 +1,481 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Random generation of functions.
+//!
+//! A generated function holds a **key**, a generator of its own that
+//! is seeded from the one it was generated from.  To compute its
+//! result for some arguments, it steers a copy of the key by the
+//! arguments and generates the result from the generator that it
+//! ends up with.  So calling it twice with the same arguments gives
+//! the same result, and calling it with different ones gives
+//! independent results.
+//!
+//! Because the key's type is fixed by the function's type rather
+//! than by the generator it came from, generated functions are
+//! `'static`, `Send` and `Sync` whatever generator generated them.
+//!
+//! `SplitRand` is implemented for boxed closures of one to six
+//! arguments, plain or `Send + Sync`, and for `Arc`'d `Send + Sync`
+//! ones.  These are all backed by a `SplitFn` keyed by a `SipRng`
+//! whose arguments steer it through `CoSplitRand`.  To pick a
+//! different key generator, or to hash the arguments instead, use
+//! `SplitFn` directly:
+//!
+//! ```
+//! use rand_split::{SplitRng, SplitRand};
+//! use rand_split::chaskeyrng::ChaskeyRng;
+//! use rand_split::function::{SplitFn, Hashed};
+//! use rand_split::siprng::SipRng;
+//! use std::sync::Arc;
+//!
+//! let mut rng = SipRng::new(1, 2);
+//!
+//! let f: Box<dyn Fn(f64, u8) -> Vec<bool>> = rng.split_gen();
+//! assert_eq!(f(0.5, 3), f(0.5, 3));
+//!
+//! let g: Arc<dyn Fn(u32) -> u64 + Send + Sync> = rng.split_gen();
+//! let h = g.clone();
+//! let y = std::thread::spawn(move || h(7)).join().unwrap();
+//! assert_eq!(y, g(7));
+//!
+//! let k: SplitFn<(String,), u64, ChaskeyRng, Hashed> = rng.split_gen();
+//! let arg = ("hello".to_string(),);
+//! assert_eq!(k.apply(&arg), k.apply(&arg));
+//! ```
+//!
+//! ## Showing and shrinking functions
+//!
+//! A closure can't be printed, so when a property that takes one
+//! fails, there's no telling what the function did.  Properties
+//! should take a `Fun` instead, in the manner of QuickCheck's `Fun`:
+//! it records each argument it's called with and the result it gave,
+//! prints them as a table, and shrinks to a finite table plus a
+//! default result for arguments not in it.
+//!
+//! ```
+//! use rand_split::check::{self, Config};
+//! use rand_split::function::Fun;
+//!
+//! let failure = check::run(&Config::default(), (1, 2), |f: Fun<u8, u8>| {
+//!     f.call(0) < 10 || f.call(1) < 10
+//! }).err().unwrap();
+//! assert_eq!(format!("{:?}", failure.shrunk), "{_ => 10}");
+//! ```
+
+use check::Shrink;
+use rand::Rand;
+use seed::siphash24;
+use siprng::SipRng;
+use std::cell::RefCell;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+use super::{SplitRng, SplitRand, CoSplitRand, Step};
+
+
+/// How a generated function steers its key by its arguments.
+pub trait Mixer<A: ?Sized> {
+    /// Steer `key` by `args`, returning the generator that the
+    /// function's result comes from.
+    fn mix<G: SplitRng>(&self, key: G, args: &A) -> G;
+}
+
+/// Steers the key by the structure of the arguments, through their
+/// `CoSplitRand` impls.  No information about the arguments is thrown
+/// away, and the functions generated don't depend on any hash
+/// function's output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Structural;
+
+impl<A: CoSplitRand + ?Sized> Mixer<A> for Structural {
+    #[inline]
+    fn mix<G: SplitRng>(&self, key: G, args: &A) -> G {
+        args.co_split_rand(key)
+    }
+}
+
+/// Hashes the arguments with a hasher built by `S`, and steers the key
+/// by all 64 bits of the hash, with `Step::CallU64`.  This works for
+/// any `Hash` arguments, but two of them whose hashes collide give
+/// the same result.
+///
+/// The default, `SipHasher24`, gives the same functions from run to
+/// run, and on every target and Rust release.  A `RandomState` would
+/// give different functions on every run, so don't use one where
+/// reproducibility matters.
+#[derive(Clone, Debug, Default)]
+pub struct Hashed<S = BuildHasherDefault<SipHasher24>>(pub S);
+
+impl<A: Hash + ?Sized, S: BuildHasher> Mixer<A> for Hashed<S> {
+    #[inline]
+    fn mix<G: SplitRng>(&self, key: G, args: &A) -> G {
+        key.step(Step::CallU64(self.0.hash_one(args)))
+    }
+}
+
+/// SipHash-2-4 under the key `(0, 0)`, as `Hashed`'s default hasher.
+/// Unlike `DefaultHasher`, its output is fixed: integers are hashed
+/// as their little-endian bytes, and `usize`s and `isize`s as 64-bit
+/// ones, so hashes don't depend on the target either.
+#[derive(Clone, Debug, Default)]
+pub struct SipHasher24 {
+    bytes: Vec<u8>,
+}
+
+impl Hasher for SipHasher24 {
+    fn finish(&self) -> u64 {
+        siphash24(0, 0, &self.bytes)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn write_u8(&mut self, i: u8) { self.write(&[i]) }
+    fn write_u16(&mut self, i: u16) { self.write(&i.to_le_bytes()) }
+    fn write_u32(&mut self, i: u32) { self.write(&i.to_le_bytes()) }
+    fn write_u64(&mut self, i: u64) { self.write(&i.to_le_bytes()) }
+    fn write_u128(&mut self, i: u128) { self.write(&i.to_le_bytes()) }
+    fn write_usize(&mut self, i: usize) { self.write_u64(i as u64) }
+    fn write_i8(&mut self, i: i8) { self.write_u8(i as u8) }
+    fn write_i16(&mut self, i: i16) { self.write_u16(i as u16) }
+    fn write_i32(&mut self, i: i32) { self.write_u32(i as u32) }
+    fn write_i64(&mut self, i: i64) { self.write_u64(i as u64) }
+    fn write_i128(&mut self, i: i128) { self.write_u128(i as u128) }
+    fn write_isize(&mut self, i: isize) { self.write_u64(i as i64 as u64) }
+}
+
+
+/// A randomly generated function from `A` to `B`, keyed by a
+/// generator of type `G` and steered by its arguments through the
+/// mixer `M`.  Functions of several arguments take them as a tuple.
+pub struct SplitFn<A: ?Sized, B, G = SipRng, M = Structural> {
+    key: G,
+    mixer: M,
+    phantom: PhantomData<fn(&A) -> B>,
+}
+
+impl<A, B, G, M> SplitFn<A, B, G, M>
+    where A: ?Sized, B: SplitRand, G: SplitRng + Clone, M: Mixer<A>
+{
+    /// A function with the given key and mixer.
+    pub fn new(key: G, mixer: M) -> SplitFn<A, B, G, M> {
+        SplitFn { key, mixer, phantom: PhantomData }
+    }
+
+    /// The function's result for `args`.
+    pub fn apply(&self, args: &A) -> B {
+        self.mixer.mix(self.key.clone(), args).split_gen()
+    }
+}
+
+/// The key is seeded from the generator, through its `Rand` impl, and
+/// the mixer is the default one.
+impl<A, B, G, M> SplitRand for SplitFn<A, B, G, M>
+    where A: ?Sized, B: SplitRand, G: SplitRng + Rand + Clone, M: Mixer<A> + Default
+{
+    fn split_rand<R: SplitRng>(rng: &mut R) -> Self {
+        SplitFn::new(Rand::rand(&mut rng.split()), M::default())
+    }
+}
+
+impl<A: ?Sized, B, G: Clone, M: Clone> Clone for SplitFn<A, B, G, M> {
+    fn clone(&self) -> Self {
+        SplitFn {
+            key: self.key.clone(),
+            mixer: self.mixer.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+
+/*
+ * Closures.  A closure of arguments `(A, B, ...)` is a `SplitFn` of
+ * the tuple `(A, B, ...)`; since tuples steer by each element in
+ * turn, a one-argument closure behaves the same as a `SplitFn` of
+ * that argument alone.
+ */
+
+macro_rules! fn_impl {
+    ($($arg:ident $ty:ident),*) => {
+        impl<$($ty,)* Z> SplitRand for Box<dyn Fn($($ty),*) -> Z>
+            where $($ty: CoSplitRand + 'static,)* Z: SplitRand + 'static
+        {
+            fn split_rand<R: SplitRng>(rng: &mut R) -> Self {
+                let f: SplitFn<($($ty,)*), Z> = rng.split_gen();
+                Box::new(move |$($arg: $ty),*| f.apply(&($($arg,)*)))
+            }
+        }
+
+        impl<$($ty,)* Z> SplitRand for Box<dyn Fn($($ty),*) -> Z + Send + Sync>
+            where $($ty: CoSplitRand + 'static,)* Z: SplitRand + 'static
+        {
+            fn split_rand<R: SplitRng>(rng: &mut R) -> Self {
+                let f: SplitFn<($($ty,)*), Z> = rng.split_gen();
+                Box::new(move |$($arg: $ty),*| f.apply(&($($arg,)*)))
+            }
+        }
+
+        impl<$($ty,)* Z> SplitRand for Arc<dyn Fn($($ty),*) -> Z + Send + Sync>
+            where $($ty: CoSplitRand + 'static,)* Z: SplitRand + 'static
+        {
+            fn split_rand<R: SplitRng>(rng: &mut R) -> Self {
+                let f: SplitFn<($($ty,)*), Z> = rng.split_gen();
+                Arc::new(move |$($arg: $ty),*| f.apply(&($($arg,)*)))
+            }
+        }
+    }
+}
+
+fn_impl!{a A}
+fn_impl!{a A, b B}
+fn_impl!{a A, b B, c C}
+fn_impl!{a A, b B, c C, d D}
+fn_impl!{a A, b B, c C, d D, e E}
+fn_impl!{a A, b B, c C, d D, e E, f F}
+
+
+/// A generated function that records its calls, so that it can be
+/// printed and shrunk.  It starts out as a `SplitFn`; shrinking
+/// replaces it with a finite table of results plus a default result.
+///
+/// The record of calls is shared between clones, so that a clone
+/// handed to a property reports its calls through the original.
+/// Calling the function again with an argument already recorded
+/// doesn't record it again.
+pub struct Fun<A, B> {
+    imp: FunImpl<A, B>,
+    calls: Rc<RefCell<Vec<(A, B)>>>,
+}
+
+#[derive(Clone)]
+enum FunImpl<A, B> {
+    Generated(SplitFn<A, B>),
+    Table(Vec<(A, B)>, B),
+}
+
+impl<A, B> Fun<A, B>
+    where A: CoSplitRand + PartialEq + Clone, B: SplitRand + Clone
+{
+    /// The function's result for `arg`.
+    pub fn call(&self, arg: A) -> B {
+        let result = match self.imp {
+            FunImpl::Generated(ref f) => f.apply(&arg),
+            FunImpl::Table(ref entries, ref default) => {
+                entries.iter()
+                    .find(|entry| entry.0 == arg)
+                    .map_or(default, |entry| &entry.1)
+                    .clone()
+            }
+        };
+        let mut calls = self.calls.borrow_mut();
+        if !calls.iter().any(|call| call.0 == arg) {
+            calls.push((arg, result.clone()));
+        }
+        result
+    }
+
+    /// The arguments this function has been called with so far, and
+    /// the results it gave, in the order of the first call with each.
+    pub fn calls(&self) -> Vec<(A, B)> {
+        self.calls.borrow().clone()
+    }
+
+    fn from_impl(imp: FunImpl<A, B>) -> Fun<A, B> {
+        Fun { imp, calls: Rc::new(RefCell::new(Vec::new())) }
+    }
+}
+
+impl<A, B> SplitRand for Fun<A, B>
+    where A: CoSplitRand + PartialEq + Clone, B: SplitRand + Clone
+{
+    fn split_rand<R: SplitRng>(rng: &mut R) -> Fun<A, B> {
+        Fun::from_impl(FunImpl::Generated(rng.split_gen()))
+    }
+}
+
+impl<A: Clone, B: Clone> Clone for Fun<A, B> {
+    fn clone(&self) -> Fun<A, B> {
+        Fun { imp: self.imp.clone(), calls: self.calls.clone() }
+    }
+}
+
+/// Prints the function as a table, like `{0 => 3, 1 => 5, _ => 0}`.
+/// A function that hasn't been shrunk shows the calls made to it
+/// so far, and `_ => ?` for the rest.
+impl<A: Debug, B: Debug> Debug for Fun<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("{")?;
+        match self.imp {
+            FunImpl::Generated(_) => {
+                for (arg, result) in self.calls.borrow().iter() {
+                    write!(f, "{:?} => {:?}, ", arg, result)?;
+                }
+                f.write_str("_ => ?")?;
+            }
+            FunImpl::Table(ref entries, ref default) => {
+                for (arg, result) in entries {
+                    write!(f, "{:?} => {:?}, ", arg, result)?;
+                }
+                write!(f, "_ => {:?}", default)?;
+            }
+        }
+        f.write_str("}")
+    }
+}
+
+/// A generated function first shrinks to the table of the calls made
+/// to it, with the first call's result as the default.  A table
+/// shrinks by dropping the entries that its last run didn't use, then
+/// by dropping single entries, then by shrinking the default and the
+/// results in the entries.
+impl<A, B> Shrink for Fun<A, B>
+    where A: CoSplitRand + PartialEq + Clone, B: SplitRand + Shrink
+{
+    fn shrink(&self) -> Vec<Fun<A, B>> {
+        let table = |entries, default| Fun::from_impl(FunImpl::Table(entries, default));
+        let calls = self.calls();
+        let mut result = Vec::new();
+        match self.imp {
+            FunImpl::Generated(_) => {
+                if let Some((_, default)) = calls.first().cloned() {
+                    result.push(table(calls[1..].to_vec(), default));
+                }
+            }
+            FunImpl::Table(ref entries, ref default) => {
+                let used: Vec<(A, B)> = entries.iter()
+                    .filter(|entry| calls.iter().any(|call| call.0 == entry.0))
+                    .cloned()
+                    .collect();
+                if used.len() < entries.len() {
+                    result.push(table(used, default.clone()));
+                }
+                for i in 0..entries.len() {
+                    let mut smaller = entries.clone();
+                    smaller.remove(i);
+                    result.push(table(smaller, default.clone()));
+                }
+                for d in default.shrink() {
+                    result.push(table(entries.clone(), d));
+                }
+                for (i, entry) in entries.iter().enumerate() {
+                    for b in entry.1.shrink() {
+                        let mut shrunk = entries.clone();
+                        shrunk[i].1 = b;
+                        result.push(table(shrunk, default.clone()));
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chaskeyrng::ChaskeyRng;
+    use siprng::SipRng;
+    use std::sync::Arc;
+    use std::thread;
+    use check::{self, Config, Shrink};
+    use super::{SplitFn, Hashed, Fun, SipHasher24};
+    use seed::siphash24;
+    use std::hash::{BuildHasher, BuildHasherDefault};
+    use ::SplitRng;
+
+    #[test]
+    fn test_closure_matches_split_fn() {
+        let f: Box<dyn Fn(u64, f32) -> Vec<u8>> = SipRng::new(3, 4).split_gen();
+        let g: SplitFn<(u64, f32), Vec<u8>> = SipRng::new(3, 4).split_gen();
+        let h: Arc<dyn Fn(u64, f32) -> Vec<u8> + Send + Sync> =
+            SipRng::new(3, 4).split_gen();
+        for i in 0..100u64 {
+            let x = (i.wrapping_mul(0x9e37_79b9_7f4a_7c15), i as f32 / 3.0);
+            assert_eq!(f(x.0, x.1), g.apply(&x));
+            assert_eq!(h(x.0, x.1), g.apply(&x));
+        }
+    }
+
+    #[test]
+    fn test_arguments_distinguished() {
+        let f: Box<dyn Fn(u8, u8) -> u64> = SipRng::new(5, 6).split_gen();
+        assert!(f(1, 2) != f(2, 1));
+
+        // Arguments that agree in their low 32 bits.
+        let g: Box<dyn Fn(u64) -> u64> = SipRng::new(5, 6).split_gen();
+        let k: SplitFn<u64, u64, ChaskeyRng, Hashed> = SipRng::new(5, 6).split_gen();
+        for i in 1..100u64 {
+            assert!(g(0) != g(i << 32));
+            assert!(k.apply(&0) != k.apply(&(i << 32)));
+        }
+    }
+
+    #[test]
+    fn test_hashed_default_is_siphash() {
+        let hasher = BuildHasherDefault::<SipHasher24>::default();
+        assert_eq!(hasher.hash_one(0x0706050403020100u64),
+                   siphash24(0, 0, &[0, 1, 2, 3, 4, 5, 6, 7]));
+        assert_eq!(hasher.hash_one(7usize), hasher.hash_one(7u64));
+        assert_eq!(hasher.hash_one("abc"), siphash24(0, 0, b"abc\xff"));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        let f: Box<dyn Fn(u32, u32, u32) -> u64 + Send + Sync> =
+            SipRng::new(7, 8).split_gen();
+        let f = Arc::new(f);
+        let expected: Vec<u64> = (0..8).map(|i| f(i, i + 1, i + 2)).collect();
+        let handles: Vec<_> = (0..8).map(|i| {
+            let f = f.clone();
+            thread::spawn(move || f(i, i + 1, i + 2))
+        }).collect();
+        let actual: Vec<u64> = handles.into_iter()
+            .map(|h| h.join().unwrap())
+            .collect();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_fun_records_calls() {
+        let f: Fun<(i32, i32), u8> = SipRng::new(9, 10).split_gen();
+        let g: Box<dyn Fn(i32, i32) -> u8> = SipRng::new(9, 10).split_gen();
+        assert_eq!(f.call((1, 2)), g(1, 2));
+        assert_eq!(f.call((3, 4)), g(3, 4));
+        f.clone().call((1, 2));
+        assert_eq!(f.calls(), vec![((1, 2), g(1, 2)), ((3, 4), g(3, 4))]);
+        assert_eq!(format!("{:?}", f),
+                   format!("{{(1, 2) => {:?}, (3, 4) => {:?}, _ => ?}}", g(1, 2), g(3, 4)));
+    }
+
+    #[test]
+    fn test_fun_shrink() {
+        let f: Fun<u8, u8> = SipRng::new(11, 12).split_gen();
+        assert!(f.shrink().is_empty());
+        let (a, b, c) = (f.call(0), f.call(1), f.call(2));
+
+        // The first shrink agrees with the function on its calls.
+        let table = f.shrink().remove(0);
+        assert_eq!((table.call(0), table.call(1), table.call(2)), (a, b, c));
+        assert_eq!(format!("{:?}", table),
+                   format!("{{1 => {:?}, 2 => {:?}, _ => {:?}}}", b, c, a));
+    }
+
+    #[test]
+    fn test_fun_check() {
+        let failure = check::run(&Config::default(), (3, 4), |f: Fun<u16, bool>| {
+            !f.call(7) || f.call(8)
+        }).expect_err("property should fail");
+        assert_eq!(format!("{:?}", failure.shrunk), "{8 => false, _ => true}");
+    }
+}
@@ -9,22 +9,50 @@
 
 use rand::{Rng, SeedableRng, Rand};
 use super::{SplitRng, SplitPrf};
-use std::marker::PhantomData;
+use chaskeyrng::ChaskeyRng;
+use seed::Seed;
+use core::hash::Hash;
+use core::marker::PhantomData;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 
 /// Wrapper that turns a `SplitRng` `S` and an `Rng` `R` into a `SplitRng`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Split<S, R> {
     rng: S,
     seq: R
 }
 
 /// The PRF type that corresponds to `Split`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Prf<F, R> {
     prf: F,
     seq: PhantomData<R>
 }
 
 
+impl<S: Rng, R: Rand> Split<S, R> {
+
+    /// Wrap a splittable RNG, seeding the sequential one off it.
+    pub(crate) fn wrap(mut rng: S) -> Split<S, R> {
+        let seq = rng.gen();
+        Split { rng, seq }
+    }
+
+}
+
+impl<R: Rng + Rand> Split<ChaskeyRng, R> {
+
+    /// The generator for `seed`, splitting with `ChaskeyRng::v1`.
+    /// The sequential generator's own output is up to the crate
+    /// that provides it.
+    pub fn v1(seed: Seed) -> Split<ChaskeyRng, R> {
+        Split::wrap(ChaskeyRng::v1(seed))
+    }
+
+}
+
 impl<S: SplitRng, R: Rng> Rng for Split<S, R> {
 
     #[inline(always)]
@@ -66,10 +94,7 @@ impl<S, SeedS, R> SeedableRng<SeedS> for Split<S, R>
     fn from_seed(seed: SeedS) -> Self {
         let mut rng = S::from_seed(seed);
         let seq = rng.gen();
-        Split {
-            rng: rng, 
-            seq: seq
-        }
+        Split { rng, seq }
     }
 }
 
@@ -83,10 +108,7 @@ impl<S, R> SplitRng for Split<S, R>
     fn split(&mut self) -> Self {
         let mut rng = self.rng.split();
         let seq = rng.gen();
-        Split {
-            rng: rng,
-            seq: seq
-        }
+        Split { rng, seq }
     }
 
     fn splitn(&mut self) -> Self::Prf {
@@ -103,12 +125,19 @@ impl<S, F, R> SplitPrf<Split<S, R>> for Prf<F, R>
           R: Rand
 {
     fn call(&self, i: u32) -> Split<S, R> {
-        let mut rng = self.prf.call(i);
-        let seq = rng.gen();
-        Split {
-            rng: rng,
-            seq: seq
-        }
+        Split::wrap(self.prf.call(i))
+    }
+
+    fn call_u64(&self, i: u64) -> Split<S, R> {
+        Split::wrap(self.prf.call_u64(i))
+    }
+
+    fn call_u128(&self, i: u128) -> Split<S, R> {
+        Split::wrap(self.prf.call_u128(i))
+    }
+
+    fn fold_in<H: Hash + ?Sized>(&self, data: &H) -> Split<S, R> {
+        Split::wrap(self.prf.fold_in(data))
     }
     
 }
@@ -117,26 +146,34 @@ impl<S: Rng + Rand, R: Rand> Rand for Split<S, R> {
     fn rand<G: Rng>(other: &mut G) -> Self {
         let mut rng: S = other.gen();
         let seq: R = rng.gen();
-        Split {
-            rng: rng,
-            seq: seq
-        }
+        Split { rng, seq }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::{Rng, XorShiftRng};
+    use rand::{Rng, SeedableRng, XorShiftRng};
     use rand::os::OsRng;
+    use seed::SeedRng;
     use siprng::SipRng;
     use super::Split;
+    #[cfg(feature = "serde")]
+    use chaskeyrng::ChaskeyRng;
 
 
     fn gen_generic_rng() -> Split<SipRng, XorShiftRng> {
-        let mut osrng = OsRng::new().ok().expect("Could not create OsRng");
+        SeedRng::from_entropy().expect("Could not read OS entropy")
+    }
+
+    fn gen_seed() -> (u64, u64) {
+        let mut osrng = OsRng::new().expect("Could not create OsRng");
         osrng.gen()
     }
 
+    fn seeded_generic_rng(seed: (u64, u64)) -> Split<SipRng, XorShiftRng> {
+        SeedableRng::from_seed(seed)
+    }
+
     #[test]
     fn test_split_rand_independence() {
         ::tests::test_split_rand_independence(&mut gen_generic_rng());
@@ -147,9 +184,56 @@ mod tests {
         ::tests::test_split_rand_closure(&mut gen_generic_rng());
     }
 
+    #[test]
+    fn test_co_split_rand() {
+        ::tests::test_co_split_rand(&mut gen_generic_rng());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        let mut osrng = OsRng::new().expect("Could not create OsRng");
+        let mut rng: Split<SipRng, ChaskeyRng> = osrng.gen();
+        ::tests::test_serde_roundtrip(&mut rng);
+    }
+
     #[test]
     fn test_split_rand_split() {
         ::tests::test_split_rand_split(&mut gen_generic_rng());
     }
 
+    #[test]
+    fn test_prf_index_domains() {
+        ::tests::test_prf_index_domains(&mut gen_generic_rng());
+    }
+
+    #[test]
+    fn test_fold_in() {
+        ::tests::test_fold_in(&mut gen_generic_rng());
+    }
+
+    #[test]
+    fn test_at_path() {
+        let seed = gen_seed();
+        ::tests::test_at_path(|| seeded_generic_rng(seed));
+    }
+
+    #[test]
+    fn test_split_rand_collections() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_collections(|| seeded_generic_rng(seed));
+    }
+
+    #[test]
+    fn test_split_rand_indexed() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_indexed(|| seeded_generic_rng(seed));
+    }
+
+    #[test]
+    fn test_split_rand_sized() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_sized(|| seeded_generic_rng(seed));
+    }
+
 }
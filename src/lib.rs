@@ -180,6 +180,57 @@
 //! order.
 //! 
 //!
+//! ## Portability
+//!
+//! The generators in this crate give the same output on every
+//! target, given the same seed and the same sequence of calls.
+//! `fill_bytes` writes each `u64` output as little-endian bytes, and
+//! `SplitRand` for `usize` and `isize` always consumes one `u32`, so
+//! it generates the same values on 32-bit and 64-bit targets: a
+//! `usize` in `0..2^32`, and an `isize` in the range of `i32`.  So
+//! programs running in lockstep on different machines stay in step.
+//!
+//! Output can change between releases when `new` or `from_seed` move
+//! to a better encoding, but each generator also has versioned
+//! constructors, such as `SipRng::v1`, whose streams never change:
+//! the generator records its `Version`, and keeps encoding its splits
+//! and calls that way after the latest version moves on.  Use those
+//! for seeds that you store.  Their raw streams, splits,
+//! PRF calls and `SplitRand` values for tuples and arrays are pinned
+//! down by the golden vectors in `tests/golden.rs`.
+//!
+//!
+//! ## Cargo features
+//!
+//! * `std` (default): everything that needs the standard library,
+//!   namely `OsRng` seeding (`entropy`, `SeedRng::from_entropy`), the
+//!   `check` and `function` modules, `SplitRand` for `HashMap` and
+//!   `HashSet`, and `std::error::Error` impls.  Implies `alloc`.
+//! * `alloc`: the pieces that need a heap but not the rest of the
+//!   standard library, namely `SplitRand` and `CoSplitRand` for
+//!   `Box`, `Vec`, `String` and `BTreeMap`, the `token` module and
+//!   `DynSplitRng`.
+//!
+//!   Without either, the crate is `#![no_std]`, and still has the
+//!   generators, their PRFs, `Split`, `SeedRng`, `AnySplitRng` and
+//!   `SplitRand` for the types in `core`.  The tests need `std`.
+//! * `derive`: `#[derive(SplitRand)]` and `#[derive(CoSplitRand)]`.
+//! * `serde`: `Serialize` and `Deserialize` for the generators and
+//!   their PRFs, which save and restore their full state.  A
+//!   restored generator continues the same stream and the same split
+//!   tree as the one that was saved, so long simulations can be
+//!   checkpointed.
+//! * `ffi`: the `ffi` module, a C interface to `SipRng` and
+//!   `ChaskeyRng`.  Implies `std`.
+//! * `trace`: `SipRng::trace_message`, which shows the message that
+//!   a `SipRng`'s next output hashes.  Every `SipRng` keeps its
+//!   whole trace with this on, so it's only meant for debugging.
+//! * `rand_core`: the `compat` module, which implements the
+//!   `rand_core` 0.6 traits for the generators, so that they can
+//!   drive `rand` 0.8 distributions, and wraps `rand_core` generators
+//!   into `SplitRng`s.
+//!
+//!
 //! ## References
 //!
 //! * Claessen, Koen and Michał H. Pałka.  2013.  ["Splittable
@@ -193,17 +244,144 @@
 //!   *Journal of Functional Programming*, Vol. 25.
 //! * The Haskell [`tf-random` library](https://hackage.haskell.org/package/tf-random).
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+extern crate core;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
 
 extern crate rand;
 
+#[cfg(feature = "derive")]
+#[allow(unused_imports)]
+#[macro_use]
+extern crate rand_split_derive;
+
+#[cfg(feature = "derive")]
+pub use rand_split_derive::{SplitRand, CoSplitRand};
+
+#[cfg(feature = "serde")]
+extern crate serde;
+
+#[cfg(feature = "rand_core")]
+extern crate rand_core;
+
+#[cfg(all(test, feature = "rand_core"))]
+extern crate rand08;
+
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+/// Implements the integer methods of `Hasher` in terms of `write`,
+/// so that the bytes hashed don't depend on the platform's byte
+/// order or pointer width.
+macro_rules! hasher_int_methods {
+    () => {
+        #[inline]
+        fn write_u8(&mut self, i: u8) {
+            self.write(&[i]);
+        }
+
+        #[inline]
+        fn write_u16(&mut self, i: u16) {
+            self.write(&::le_bytes(i as u64)[..2]);
+        }
+
+        #[inline]
+        fn write_u32(&mut self, i: u32) {
+            self.write(&::le_bytes(i as u64)[..4]);
+        }
+
+        #[inline]
+        fn write_u64(&mut self, i: u64) {
+            self.write(&::le_bytes(i));
+        }
+
+        #[inline]
+        fn write_u128(&mut self, i: u128) {
+            let mut bytes = [0u8; 16];
+            bytes[..8].copy_from_slice(&::le_bytes(i as u64));
+            bytes[8..].copy_from_slice(&::le_bytes((i >> 64) as u64));
+            self.write(&bytes);
+        }
+
+        #[inline]
+        fn write_usize(&mut self, i: usize) {
+            self.write_u64(i as u64);
+        }
+
+        #[inline]
+        fn write_i8(&mut self, i: i8) {
+            self.write_u8(i as u8);
+        }
+
+        #[inline]
+        fn write_i16(&mut self, i: i16) {
+            self.write_u16(i as u16);
+        }
+
+        #[inline]
+        fn write_i32(&mut self, i: i32) {
+            self.write_u32(i as u32);
+        }
+
+        #[inline]
+        fn write_i64(&mut self, i: i64) {
+            self.write_u64(i as u64);
+        }
+
+        #[inline]
+        fn write_i128(&mut self, i: i128) {
+            self.write_u128(i as u128);
+        }
+
+        #[inline]
+        fn write_isize(&mut self, i: isize) {
+            self.write_u64(i as i64 as u64);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub mod check;
+#[cfg(feature = "rand_core")]
+pub mod compat;
+pub mod dynamic;
+#[cfg(feature = "std")]
+pub mod entropy;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod function;
 pub mod generic;
+pub mod seed;
 pub mod siprng;
 pub mod chaskeyrng;
+#[cfg(feature = "alloc")]
+pub mod token;
 pub mod twolcg;
 
 use rand::{Rng, Rand};
 use chaskeyrng::{ChaskeyRng, ChaskeyPrf};
-use std::hash::{Hash, Hasher, SipHasher};
+use core::array;
+use core::hash::Hash;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::hash::BuildHasher;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 
 /// A wrapper that generically adds splittability to RNGs.
@@ -233,10 +411,104 @@ pub trait SplitRng : Rng + Sized {
     
     /// Split a second RNG off this one.
     fn split(&mut self) -> Self;
+
+    /// Split a second RNG off this one, with some `Hash` data mixed
+    /// into it.  This is the same as `self.splitn().fold_in(data)`,
+    /// so like `split` it advances this generator; to derive several
+    /// generators from the same state, call `SplitPrf::fold_in` on a
+    /// PRF instead.
+    fn fold_in<H: Hash + ?Sized>(&mut self, data: &H) -> Self {
+        self.splitn().fold_in(data)
+    }
     
     fn split_gen<A: SplitRand>(&mut self) -> A {
         SplitRand::split_rand::<Self>(self)
     }
+
+    /// Generate a value with the given size budget.  See
+    /// `SplitRand::split_rand_sized`.
+    fn split_gen_sized<A: SplitRand>(&mut self, size: usize) -> A {
+        SplitRand::split_rand_sized::<Self>(self, size)
+    }
+
+    /// Take one step down the split tree, consuming this generator
+    /// and returning the one at that node.  See `Step` for what each
+    /// step means.
+    fn step(mut self, step: Step) -> Self {
+        match step {
+            Step::Left => { self.split(); self }
+            Step::Right => self.split(),
+            Step::Call(i) => self.splitn().call(i),
+            Step::CallU64(i) => self.splitn().call_u64(i),
+            Step::CallU128(i) => self.splitn().call_u128(i),
+        }
+    }
+
+    /// Jump straight to a node of the split tree, by taking each
+    /// step in `path` in turn.  Plain `u32`s in the path stand for
+    /// `Step::Call`, so `rng.at_path(&[1, 0, 3])` is the same as
+    /// `rng.splitn().call(1).splitn().call(0).splitn().call(3)`.
+    ///
+    /// This is meant for reproducing a generator deep inside some
+    /// computation from its root seed and a logged path.
+    fn at_path<S>(self, path: &[S]) -> Self
+        where S: Copy + Into<Step>
+    {
+        path.iter().fold(self, |rng, &step| rng.step(step.into()))
+    }
+}
+
+/// One step down the tree of generators that a `SplitRng` spawns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// The parent generator, as it is left after a `split`.
+    Left,
+    /// The child generator that `split` returns.
+    Right,
+    /// The generator that `splitn().call(i)` returns.
+    Call(u32),
+    /// The generator that `splitn().call_u64(i)` returns.
+    CallU64(u64),
+    /// The generator that `splitn().call_u128(i)` returns.
+    CallU128(u128),
+}
+
+impl From<u32> for Step {
+    fn from(i: u32) -> Step {
+        Step::Call(i)
+    }
+}
+
+/// A version of the generators' encodings: how a seed becomes a key,
+/// and how splits and PRF calls become output.  Each generator
+/// records the version it was built with, and every place that
+/// encodes matches on it, so a generator built with `V1` gives the
+/// same streams in every release.  See the `v1` constructors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Version {
+    /// The first version, pinned down by `tests/golden.rs`.
+    V1,
+}
+
+impl Version {
+    /// The version that `new` and `from_seed` use.
+    pub const LATEST: Version = Version::V1;
+
+    /// The version's number, as trace headers and tokens record it.
+    pub const fn number(self) -> u32 {
+        match self {
+            Version::V1 => 1,
+        }
+    }
+
+    /// The version numbered `n`, if there is one.
+    pub fn from_number(n: u32) -> Option<Version> {
+        match n {
+            1 => Some(Version::V1),
+            _ => None,
+        }
+    }
 }
 
 /// Pseudo-random functions ("PRFs") generated off a `SplitRng`.
@@ -250,8 +522,26 @@ pub trait SplitRng : Rng + Sized {
 /// Note that while the term *pseudo-random function* has a
 /// technical meaning in cryptograpy, **no security claim is
 /// implied here**.
+///
+/// PRFs accept `u32`, `u64` and `u128` arguments.  The three index
+/// spaces are **domain-separated**: for example, `call(5)`,
+/// `call_u64(5)` and `call_u128(5)` produce three different
+/// generators.
 pub trait SplitPrf<Rng> {
+    /// Construct the generator at a `u32` index.
     fn call(&self, i: u32) -> Rng;
+
+    /// Construct the generator at a `u64` index.
+    fn call_u64(&self, i: u64) -> Rng;
+
+    /// Construct the generator at a `u128` index.
+    fn call_u128(&self, i: u128) -> Rng;
+
+    /// Construct the generator for some `Hash` data.  Unlike hashing
+    /// the data down to an index and calling `call`, no information
+    /// about the data is thrown away, so distinct data collide no
+    /// more often than the underlying hash function does.
+    fn fold_in<H: Hash + ?Sized>(&self, data: &H) -> Rng;
 }
 
 /// A type that can be randomly generated from a `SplitRand`.
@@ -262,6 +552,29 @@ pub trait SplitRand {
     /// Generates a random instance of this type using the given
     /// source of randomness.
     fn split_rand<R: SplitRng>(rng: &mut R) -> Self;
+
+    /// Generates a random instance of this type whose size is
+    /// bounded by `size`.  This is how recursive types keep from
+    /// growing without bound: each node spends some of the budget
+    /// and divides the rest between its children, much like
+    /// QuickCheck's `sized` and `resize`.
+    ///
+    /// What the budget means is up to each type.  Tuples and arrays
+    /// pass it on to each of their elements.  Collections pick a
+    /// length no greater than `size` and divide what's left of the
+    /// budget evenly between their elements, so nested collections
+    /// have at most `size` elements in total.  The default
+    /// implementation ignores the budget, which suits types that
+    /// have no size to speak of.
+    ///
+    /// Implementations must take the budget from the generator they
+    /// were given in the same way `split_rand` does, so that siblings
+    /// stay independent of each other.
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, _size: usize) -> Self
+        where Self: Sized
+    {
+        Self::split_rand(rng)
+    }
     
 }
 
@@ -280,24 +593,23 @@ impl<A: Rand> SplitRand for Seq<A> {
 }
 
 
-impl<A: Hash, B: Rand> SplitRand for Box<Fn(A) -> B> {
-    
-    fn split_rand<R>(rng: &mut R) -> Self 
-        where R: SplitRng, R: 'static
-    {
-        let (k0, k1) = (rng.next_u64(), rng.next_u64());
-        let prf = rng.splitn();
-        Box::new(move |arg: A| {
-            let i: u32 = {
-                // TODO: is there a way not to hardcode `SipHasher` here?
-                let mut hasher = SipHasher::new_with_keys(k0, k1);
-                arg.hash(&mut hasher);
-                (hasher.finish() & 0xffff_ffff) as u32
-            };
-            Rand::rand(&mut prf.call(i))
-        })
-    }
+/// The little-endian bytes of a `u64`.
+#[inline]
+fn le_bytes(n: u64) -> [u8; 8] {
+    [n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8,
+     (n >> 32) as u8, (n >> 40) as u8, (n >> 48) as u8, (n >> 56) as u8]
+}
 
+/// Fill `dest` with the little-endian bytes of successive `next_u64`
+/// outputs, discarding the unused end of the last one.  The
+/// generators' `fill_bytes` all do this, so that byte output doesn't
+/// depend on the host's endianness.
+#[inline]
+fn fill_bytes_le<R: Rng + ?Sized>(rng: &mut R, dest: &mut [u8]) {
+    for chunk in dest.chunks_mut(8) {
+        let block = le_bytes(rng.next_u64());
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
 }
 
 /// A macro that implements `SplitRand` sequentially for any type that
@@ -317,44 +629,95 @@ macro_rules! split_rand_seq_impl {
     }
 }
 
-split_rand_seq_impl!{isize}
 split_rand_seq_impl!{i8}
 split_rand_seq_impl!{i16}
 split_rand_seq_impl!{i32}
 split_rand_seq_impl!{i64}
 
-split_rand_seq_impl!{usize}
 split_rand_seq_impl!{u8}
 split_rand_seq_impl!{u16}
 split_rand_seq_impl!{u32}
 split_rand_seq_impl!{u64}
 
-split_rand_seq_impl!{f32}
-split_rand_seq_impl!{f64}
+/*
+ * `Rand` for `usize` and `isize` reads one word on 32-bit targets and
+ * two on 64-bit ones, so we don't use it.  These always consume one
+ * `u32`, and so give values that every pointer width can represent:
+ * the same ones on every target, with everything generated after
+ * them staying in step.  The floats are spelled out
+ * for the same reason, with the bit patterns that `Rng::next_f32` and
+ * `Rng::next_f64` use, rather than trusting each `Rng`'s overrides.
+ */
+
+/// Uniform in `0..2^32`, the same on every target.
+impl SplitRand for usize {
+    #[inline]
+    fn split_rand<R: SplitRng>(rng: &mut R) -> usize {
+        rng.next_u32() as usize
+    }
+}
+
+/// Uniform in the range of `i32`, the same on every target.
+impl SplitRand for isize {
+    #[inline]
+    fn split_rand<R: SplitRng>(rng: &mut R) -> isize {
+        rng.next_u32() as i32 as isize
+    }
+}
+
+/// Uniform in `[0, 1)`, from 23 bits of a `u32`.
+impl SplitRand for f32 {
+    #[inline]
+    fn split_rand<R: SplitRng>(rng: &mut R) -> f32 {
+        f32::from_bits(0x3f80_0000 | (rng.next_u32() & 0x007f_ffff)) - 1.0
+    }
+}
+
+/// Uniform in `[0, 1)`, from 52 bits of a `u64`.
+impl SplitRand for f64 {
+    #[inline]
+    fn split_rand<R: SplitRng>(rng: &mut R) -> f64 {
+        f64::from_bits(0x3ff0_0000_0000_0000 | (rng.next_u64() & 0x000f_ffff_ffff_ffff)) - 1.0
+    }
+}
 // TODO: Open01, Closed01
 
 split_rand_seq_impl!{char}
 split_rand_seq_impl!{bool}
 
 /*
- * These macros are more or less adapted from the `rand` crate.
+ * Tuples and arrays split a PRF off the generator and generate
+ * element `k` from `call(k)`.  This takes one `splitn` per value no
+ * matter how many elements it has, and puts every element one level
+ * below its parent in the split tree.
  */
 
 macro_rules! tuple_impl {
     // use variables to indicate the arity of the tuple
-    ($($tyvar:ident),* ) => {
+    ($($tyvar:ident $k:expr),* ) => {
         // the trailing commas are for the 1 tuple
         impl<
             $( $tyvar : SplitRand ),*
             > SplitRand for ( $( $tyvar ),* , ) {
 
             #[inline]
-            fn split_rand<R: SplitRng>(_rng: &mut R) -> ( $( $tyvar ),* , ) {
+            fn split_rand<R: SplitRng>(rng: &mut R) -> ( $( $tyvar ),* , ) {
+                let prf = rng.splitn();
                 (
-                    // use the $tyvar's to get the appropriate number of
-                    // repeats (they're not actually needed)
                     $(
-                        _rng.split().split_gen::<$tyvar>()
+                        prf.call($k).split_gen::<$tyvar>()
+                    ),*
+                    ,
+                )
+            }
+
+            #[inline]
+            fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize)
+                                             -> ( $( $tyvar ),* , ) {
+                let prf = rng.splitn();
+                (
+                    $(
+                        prf.call($k).split_gen_sized::<$tyvar>(size)
                     ),*
                     ,
                 )
@@ -364,51 +727,425 @@ macro_rules! tuple_impl {
 }
 
 impl SplitRand for () {
-    fn split_rand<R: SplitRng>(_: &mut R) -> () { () }
+    fn split_rand<R: SplitRng>(_: &mut R) {}
+}
+
+tuple_impl!{A 0}
+tuple_impl!{A 0, B 1}
+tuple_impl!{A 0, B 1, C 2}
+tuple_impl!{A 0, B 1, C 2, D 3}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+            M 12}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+            M 12, N 13}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+            M 12, N 13, O 14}
+tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+            M 12, N 13, O 14, P 15}
+
+
+impl<T: SplitRand, const N: usize> SplitRand for [T; N] {
+    #[inline]
+    fn split_rand<R: SplitRng>(rng: &mut R) -> [T; N] {
+        let prf = rng.splitn();
+        array::from_fn(|k| prf.call(k as u32).split_gen())
+    }
+
+    #[inline]
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> [T; N] {
+        let prf = rng.splitn();
+        array::from_fn(|k| prf.call(k as u32).split_gen_sized(size))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: SplitRand> SplitRand for Box<T> {
+    #[inline]
+    fn split_rand<R: SplitRng>(rng: &mut R) -> Box<T> {
+        Box::new(rng.split_gen())
+    }
+
+    #[inline]
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> Box<T> {
+        Box::new(rng.split_gen_sized(size))
+    }
+}
+
+
+/*
+ * Collections.  These are generated so that their length or shape
+ * comes from one substream, and element `i` comes from `call_u64(i)`
+ * on a PRF.  So a longer collection generated from the same generator
+ * shares a prefix with a shorter one, and changing how the length is
+ * picked doesn't change the elements.
+ */
+
+/// The longest collection that the collection impls generate, plus
+/// one.
+#[cfg(feature = "alloc")]
+const MAX_LEN: u32 = 32;
+
+/// Pick a collection length, uniformly from `0..MAX_LEN`.
+#[cfg(feature = "alloc")]
+fn split_rand_len<R: SplitRng>(rng: &mut R) -> usize {
+    (rng.next_u32() % MAX_LEN) as usize
+}
+
+/// The longest collection that the collection impls generate from
+/// a size budget.  Without a cap, a budget like `usize::MAX` would
+/// ask for a collection too big to allocate.
+#[cfg(feature = "alloc")]
+const MAX_SIZED_LEN: u32 = 1 << 16;
+
+/// Pick a collection length, uniformly from `0..size + 1`, capped at
+/// `MAX_SIZED_LEN`.
+#[cfg(feature = "alloc")]
+fn split_rand_len_sized<R: SplitRng>(rng: &mut R, size: usize) -> usize {
+    let max = size.min(MAX_SIZED_LEN as usize) as u32;
+    (rng.next_u32() % (max + 1)) as usize
+}
+
+/// Generate the elements of a collection from a PRF.  `size` is the
+/// budget for each element, if any.
+#[cfg(feature = "alloc")]
+fn split_rand_elems<R, T>(prf: &R::Prf, len: usize, size: Option<usize>)
+                          -> Vec<T>
+    where R: SplitRng, T: SplitRand
+{
+    (0..len).map(|i| {
+        let mut rng = prf.call_u64(i as u64);
+        match size {
+            None => rng.split_gen(),
+            Some(size) => rng.split_gen_sized(size),
+        }
+    }).collect()
+}
+
+/// The length is picked uniformly from 0 to 31; element `i` is
+/// generated from `splitn().call_u64(i)`.  With a size budget, the
+/// length is picked from 0 to `size` (but no more than 65536), and
+/// the rest of the budget is divided evenly between the elements.
+#[cfg(feature = "alloc")]
+impl<T: SplitRand> SplitRand for Vec<T> {
+    fn split_rand<R: SplitRng>(rng: &mut R) -> Vec<T> {
+        let len = split_rand_len(&mut rng.split());
+        split_rand_elems::<R, T>(&rng.splitn(), len, None)
+    }
+
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> Vec<T> {
+        let len = split_rand_len_sized(&mut rng.split(), size);
+        let elem_size = (size - len).checked_div(len).unwrap_or(0);
+        split_rand_elems::<R, T>(&rng.splitn(), len, Some(elem_size))
+    }
+}
+
+/// Generated like a `Vec<char>`.
+#[cfg(feature = "alloc")]
+impl SplitRand for String {
+    fn split_rand<R: SplitRng>(rng: &mut R) -> String {
+        let chars: Vec<char> = SplitRand::split_rand(rng);
+        chars.into_iter().collect()
+    }
+
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> String {
+        let chars: Vec<char> = SplitRand::split_rand_sized(rng, size);
+        chars.into_iter().collect()
+    }
+}
+
+/// Whether the result is `Some` doesn't affect the value inside.
+impl<T: SplitRand> SplitRand for Option<T> {
+    fn split_rand<R: SplitRng>(rng: &mut R) -> Option<T> {
+        let prf = rng.splitn();
+        if prf.call(0).gen() {
+            Some(prf.call(1).split_gen())
+        } else {
+            None
+        }
+    }
+
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> Option<T> {
+        let prf = rng.splitn();
+        if prf.call(0).gen() {
+            Some(prf.call(1).split_gen_sized(size))
+        } else {
+            None
+        }
+    }
+}
+
+/// `Ok` and `Err` values come from separate substreams.
+impl<T: SplitRand, E: SplitRand> SplitRand for Result<T, E> {
+    fn split_rand<R: SplitRng>(rng: &mut R) -> Result<T, E> {
+        let prf = rng.splitn();
+        if prf.call(0).gen() {
+            Ok(prf.call(1).split_gen())
+        } else {
+            Err(prf.call(2).split_gen())
+        }
+    }
+
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> Result<T, E> {
+        let prf = rng.splitn();
+        if prf.call(0).gen() {
+            Ok(prf.call(1).split_gen_sized(size))
+        } else {
+            Err(prf.call(2).split_gen_sized(size))
+        }
+    }
 }
 
-tuple_impl!{A}
-tuple_impl!{A, B}
-tuple_impl!{A, B, C}
-tuple_impl!{A, B, C, D}
-tuple_impl!{A, B, C, D, E}
-tuple_impl!{A, B, C, D, E, F}
-tuple_impl!{A, B, C, D, E, F, G}
-tuple_impl!{A, B, C, D, E, F, G, H}
-tuple_impl!{A, B, C, D, E, F, G, H, I}
-tuple_impl!{A, B, C, D, E, F, G, H, I, J}
-tuple_impl!{A, B, C, D, E, F, G, H, I, J, K}
-tuple_impl!{A, B, C, D, E, F, G, H, I, J, K, L}
+/// Generated like a `Vec<(K, V)>`.  When a key repeats, its first
+/// value wins, so that entries don't change as more are added.
+#[cfg(feature = "std")]
+impl<K, V, S> SplitRand for HashMap<K, V, S>
+    where K: SplitRand + Eq + Hash,
+          V: SplitRand,
+          S: BuildHasher + Default
+{
+    fn split_rand<R: SplitRng>(rng: &mut R) -> HashMap<K, V, S> {
+        from_entries(SplitRand::split_rand(rng))
+    }
 
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> HashMap<K, V, S> {
+        from_entries(SplitRand::split_rand_sized(rng, size))
+    }
+}
 
-// Adapted, with minor modifications, from the `rand` crate.
-macro_rules! array_impl {
-    {$n:expr, $t:ident, $($ts:ident,)*} => {
-        array_impl!{($n - 1), $($ts,)*}
+#[cfg(feature = "std")]
+fn from_entries<K, V, S>(entries: Vec<(K, V)>) -> HashMap<K, V, S>
+    where K: Eq + Hash, S: BuildHasher + Default
+{
+    let mut result = HashMap::with_hasher(S::default());
+    for (k, v) in entries {
+        result.entry(k).or_insert(v);
+    }
+    result
+}
 
-        impl<T> SplitRand for [T; $n] where T: SplitRand {
+/// Generated like a `Vec<(K, V)>`.  When a key repeats, its first
+/// value wins, so that entries don't change as more are added.
+#[cfg(feature = "alloc")]
+impl<K, V> SplitRand for BTreeMap<K, V>
+    where K: SplitRand + Ord,
+          V: SplitRand
+{
+    fn split_rand<R: SplitRng>(rng: &mut R) -> BTreeMap<K, V> {
+        from_sorted_entries(SplitRand::split_rand(rng))
+    }
+
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> BTreeMap<K, V> {
+        from_sorted_entries(SplitRand::split_rand_sized(rng, size))
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn from_sorted_entries<K: Ord, V>(entries: Vec<(K, V)>) -> BTreeMap<K, V> {
+    let mut result = BTreeMap::new();
+    for (k, v) in entries {
+        result.entry(k).or_insert(v);
+    }
+    result
+}
+
+/// Generated like a `Vec<T>`.
+#[cfg(feature = "std")]
+impl<T, S> SplitRand for HashSet<T, S>
+    where T: SplitRand + Eq + Hash,
+          S: BuildHasher + Default
+{
+    fn split_rand<R: SplitRng>(rng: &mut R) -> HashSet<T, S> {
+        let elems: Vec<T> = SplitRand::split_rand(rng);
+        elems.into_iter().collect()
+    }
+
+    fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> HashSet<T, S> {
+        let elems: Vec<T> = SplitRand::split_rand_sized(rng, size);
+        elems.into_iter().collect()
+    }
+}
+
+
+/*
+ * Coarbitrary.  A `CoSplitRand` value steers a generator down the
+ * split tree by its structure, one step per piece of the value.
+ */
+
+/// Types whose values can steer a `SplitRng`, the counterpart of
+/// QuickCheck's `CoArbitrary`.  This is what `SplitRand` uses to
+/// generate functions: each argument steers a generator to a node of
+/// its own, and the result is generated from there.
+///
+/// Implementations should steer distinct values to distinct nodes,
+/// and do so by the value's structure: an integer steers by a PRF
+/// index, an `Option` by which variant it is and then by its
+/// contents, a sequence by its length and then by each element in
+/// turn.  No information about the value is thrown away, and types
+/// that aren't `Hash`, like floats, can be used as arguments too.
+///
+/// With the `derive` feature, this can be derived for structs and
+/// enums.
+pub trait CoSplitRand {
+    /// Steer `rng` by this value, returning the generator it leads
+    /// to.
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R;
+}
+
+macro_rules! co_split_rand_impl {
+    ($t:ty, $x:ident => $step:expr) => {
+        impl CoSplitRand for $t {
             #[inline]
-            fn split_rand<R: SplitRng>(_rng: &mut R) -> [T; $n] {
-                [
-                    _rng.split().split_gen::<$t>(), 
-                    $(_rng.split().split_gen::<$ts>()),*
-                ]
+            fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+                let $x = *self;
+                rng.step($step)
             }
         }
-    };
-    {$n:expr,} => {
-        impl<T> SplitRand for [T; $n] {
-            fn split_rand<R: SplitRng>(_rng: &mut R) -> [T; $n] { [] }
+    }
+}
+
+co_split_rand_impl!{u8, x => Step::Call(x as u32)}
+co_split_rand_impl!{u16, x => Step::Call(x as u32)}
+co_split_rand_impl!{u32, x => Step::Call(x)}
+co_split_rand_impl!{u64, x => Step::CallU64(x)}
+co_split_rand_impl!{u128, x => Step::CallU128(x)}
+co_split_rand_impl!{usize, x => Step::CallU64(x as u64)}
+
+co_split_rand_impl!{i8, x => Step::Call(x as u32)}
+co_split_rand_impl!{i16, x => Step::Call(x as u32)}
+co_split_rand_impl!{i32, x => Step::Call(x as u32)}
+co_split_rand_impl!{i64, x => Step::CallU64(x as u64)}
+co_split_rand_impl!{i128, x => Step::CallU128(x as u128)}
+co_split_rand_impl!{isize, x => Step::CallU64(x as i64 as u64)}
+
+// Floats steer by their bits, so `0.0` and `-0.0` are different
+// arguments, as are NaNs with different payloads.
+co_split_rand_impl!{f32, x => Step::Call(x.to_bits())}
+co_split_rand_impl!{f64, x => Step::CallU64(x.to_bits())}
+
+co_split_rand_impl!{char, x => Step::Call(x as u32)}
+co_split_rand_impl!{bool, x => Step::Call(x as u32)}
+
+impl CoSplitRand for () {
+    #[inline]
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R { rng }
+}
+
+impl<T: CoSplitRand + ?Sized> CoSplitRand for &T {
+    #[inline]
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        (**self).co_split_rand(rng)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: CoSplitRand + ?Sized> CoSplitRand for Box<T> {
+    #[inline]
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        (**self).co_split_rand(rng)
+    }
+}
+
+/// Tuples steer by each element in turn.
+macro_rules! co_tuple_impl {
+    ($($tyvar:ident $k:tt),*) => {
+        impl<$( $tyvar : CoSplitRand ),*> CoSplitRand for ( $( $tyvar ),* , ) {
+            #[inline]
+            fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+                $( let rng = self.$k.co_split_rand(rng); )*
+                rng
+            }
         }
-    };
+    }
 }
 
-array_impl!{
-    32, 
-    T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, 
-    T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T,
+co_tuple_impl!{A 0}
+co_tuple_impl!{A 0, B 1}
+co_tuple_impl!{A 0, B 1, C 2}
+co_tuple_impl!{A 0, B 1, C 2, D 3}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+               M 12}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+               M 12, N 13}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+               M 12, N 13, O 14}
+co_tuple_impl!{A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11,
+               M 12, N 13, O 14, P 15}
+
+/// Arrays have a fixed length, so they just steer by each element in
+/// turn.
+impl<T: CoSplitRand, const N: usize> CoSplitRand for [T; N] {
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        self.iter().fold(rng, |rng, x| x.co_split_rand(rng))
+    }
+}
+
+/// Steers by the length, then by each element in turn.  Steering by
+/// the length first keeps a slice from steering to the same node as
+/// a longer one that it's a prefix of.
+impl<T: CoSplitRand> CoSplitRand for [T] {
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        let rng = rng.step(Step::CallU64(self.len() as u64));
+        self.iter().fold(rng, |rng, x| x.co_split_rand(rng))
+    }
 }
 
+#[cfg(feature = "alloc")]
+impl<T: CoSplitRand> CoSplitRand for Vec<T> {
+    #[inline]
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        self.as_slice().co_split_rand(rng)
+    }
+}
+
+/// Strings steer in one step, by `fold_in`.
+impl CoSplitRand for str {
+    #[inline]
+    fn co_split_rand<R: SplitRng>(&self, mut rng: R) -> R {
+        rng.fold_in(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl CoSplitRand for String {
+    #[inline]
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        self.as_str().co_split_rand(rng)
+    }
+}
+
+impl<T: CoSplitRand> CoSplitRand for Option<T> {
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        match *self {
+            None => rng.step(Step::Call(0)),
+            Some(ref x) => x.co_split_rand(rng.step(Step::Call(1))),
+        }
+    }
+}
+
+impl<T: CoSplitRand, E: CoSplitRand> CoSplitRand for Result<T, E> {
+    fn co_split_rand<R: SplitRng>(&self, rng: R) -> R {
+        match *self {
+            Ok(ref x) => x.co_split_rand(rng.step(Step::Call(0))),
+            Err(ref e) => e.co_split_rand(rng.step(Step::Call(1))),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -416,7 +1153,26 @@ mod tests {
     //! children modules.
 
     use rand::SeedableRng;
-    use ::{SplitRng, SplitPrf, SplitRand};
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use ::{SplitRng, SplitPrf, SplitRand, CoSplitRand, Step, MAX_SIZED_LEN};
+    #[cfg(feature = "serde")]
+    use serde::{Serialize, de::DeserializeOwned};
+    #[cfg(feature = "serde")]
+    use serde_json;
+
+    /// Check output that must be bit-identical on every target: the
+    /// bytes that `fill_bytes` gives, then a `usize`, an `isize`, an
+    /// `f32` and an `f64`.  `floats` are the floats' bits.
+    pub fn check_portable_output<R: SplitRng>(mut rng: R, bytes: [u8; 13],
+                                              ints: (usize, isize), floats: (u32, u64)) {
+        let mut actual = [0u8; 13];
+        rng.fill_bytes(&mut actual);
+        assert_eq!(actual, bytes);
+
+        let (u, i, f, d): (usize, isize, f32, f64) = rng.split_gen();
+        assert_eq!((u, i), ints);
+        assert_eq!((f.to_bits(), d.to_bits()), floats);
+    }
 
     /// Test that generation of tuple elements with `SplitRand` is
     /// independent.
@@ -457,9 +1213,245 @@ mod tests {
         }
     }
 
+    /// Test that the `u32`, `u64` and `u128` index spaces of a PRF
+    /// are reproducible and don't collide with each other.
+    pub fn test_prf_index_domains<R: SplitRng>(rng: &mut R) {
+        let prf = rng.splitn();
+        let i: u32 = rng.gen();
+
+        let mut ra = prf.call(i);
+        let mut rb = prf.call_u64(i as u64);
+        let mut rc = prf.call_u128(i as u128);
+        let mut rd = prf.call_u64(i as u64);
+        let mut re = prf.call_u128(i as u128);
+
+        let a: Vec<u64> = ra.gen_iter().take(16).collect();
+        let b: Vec<u64> = rb.gen_iter().take(16).collect();
+        let c: Vec<u64> = rc.gen_iter().take(16).collect();
+        assert!(a != b);
+        assert!(a != c);
+        assert!(b != c);
+        assert!(iter_eq(b, rd.gen_iter::<u64>().take(16)));
+        assert!(iter_eq(c, re.gen_iter::<u64>().take(16)));
+
+        // Indexes that differ only in their high bits must not
+        // collide either.
+        let j: u64 = rng.gen();
+        let mut rf = prf.call_u64(j);
+        let mut rg = prf.call_u64(j ^ (1 << 63));
+        let mut rh = prf.call_u128(j as u128);
+        let mut ri = prf.call_u128((j as u128) | (1 << 127));
+        assert!(!iter_eq(rf.gen_iter::<u64>().take(16),
+                         rg.gen_iter::<u64>().take(16)));
+        assert!(!iter_eq(rh.gen_iter::<u64>().take(16),
+                         ri.gen_iter::<u64>().take(16)));
+    }
+
+    /// Test that `at_path` lands on the same generator as taking the
+    /// equivalent steps by hand.  `mk` must return a fresh copy of
+    /// the same generator every time it's called.
+    pub fn test_at_path<R, F>(mk: F)
+        where R: SplitRng, F: Fn() -> R
+    {
+        let (i, j, k0): (u32, u64, u64) = mk().gen();
+        let k = ((k0 as u128) << 64) | j as u128;
+
+        let mut ra = mk().at_path(&[i, 0, 7]);
+        let mut rb = mk().splitn().call(i).splitn().call(0).splitn().call(7);
+        assert!(iter_eq(ra.gen_iter::<u64>().take(16),
+                        rb.gen_iter::<u64>().take(16)));
+
+        let path = [Step::Right, Step::CallU64(j), Step::Left,
+                    Step::CallU128(k), Step::Call(i), Step::Right];
+        let mut rc = mk().at_path(&path);
+        let mut rd = {
+            let mut r = mk().split().splitn().call_u64(j);
+            r.split();
+            r.splitn().call_u128(k).splitn().call(i).split()
+        };
+        assert!(iter_eq(rc.gen_iter::<u64>().take(16),
+                        rd.gen_iter::<u64>().take(16)));
+
+        let mut re = mk().at_path(&[Step::Left]);
+        let mut rf = mk().at_path(&[Step::Right]);
+        assert!(!iter_eq(re.gen_iter::<u64>().take(16),
+                         rf.gen_iter::<u64>().take(16)));
+    }
+
+    /// Test that `fold_in` is reproducible and that different data
+    /// give different generators.
+    pub fn test_fold_in<R: SplitRng>(rng: &mut R) {
+        let prf = rng.splitn();
+
+        let mut ra = prf.fold_in("physics");
+        let mut rb = prf.fold_in("physics");
+        let mut rc = prf.fold_in("render");
+        let a: Vec<u64> = ra.gen_iter().take(16).collect();
+        assert!(iter_eq(a.iter().cloned(), rb.gen_iter::<u64>().take(16)));
+        assert!(!iter_eq(a.iter().cloned(), rc.gen_iter::<u64>().take(16)));
+
+        let mut rd = prf.fold_in(&(1u32, 2u32));
+        let mut re = prf.fold_in(&(2u32, 1u32));
+        let mut rf = prf.fold_in(&1u32).fold_in(&2u32);
+        let d: Vec<u64> = rd.gen_iter().take(16).collect();
+        assert!(!iter_eq(d.iter().cloned(), re.gen_iter::<u64>().take(16)));
+        assert!(!iter_eq(d.iter().cloned(), rf.gen_iter::<u64>().take(16)));
+
+        // Hashing a `u32` isn't the same as calling with it.
+        let i: u32 = rng.gen();
+        let mut rg = prf.fold_in(&i);
+        let mut rh = prf.call(i);
+        assert!(!iter_eq(rg.gen_iter::<u64>().take(16),
+                         rh.gen_iter::<u64>().take(16)));
+    }
+
+    /// Test that collections are generated element by element from
+    /// a PRF, independently of their length.  `mk` must return a
+    /// fresh copy of the same generator every time it's called.
+    pub fn test_split_rand_collections<R, F>(mk: F)
+        where R: SplitRng, F: Fn() -> R
+    {
+        for i in 0..10 {
+            let mk = || mk().at_path(&[i]);
+
+            let a: Vec<u64> = mk().split_gen();
+            let b: Vec<bool> = mk().split_gen();
+            assert_eq!(a.len(), b.len());
+
+            let mut rng = mk();
+            rng.split();
+            let prf = rng.splitn();
+            for (j, x) in a.iter().enumerate() {
+                assert_eq!(*x, prf.call_u64(j as u64).split_gen::<u64>());
+            }
+
+            let s: String = mk().split_gen();
+            assert_eq!(s.chars().count(), a.len());
+
+            let set: HashSet<u64> = mk().split_gen();
+            assert_eq!(set.len(), a.len());
+
+            let pairs: Vec<(u8, u64)> = mk().split_gen();
+            let map: HashMap<u8, u64> = mk().split_gen();
+            let btree: BTreeMap<u8, u64> = mk().split_gen();
+            assert_eq!(map.len(), btree.len());
+            for &(k, _) in pairs.iter() {
+                assert!(map.contains_key(&k));
+            }
+            for (k, v) in map {
+                let first = pairs.iter().find(|&&(pk, _)| pk == k);
+                assert_eq!(first.map(|&(_, pv)| pv), Some(v));
+                assert_eq!(btree[&k], v);
+            }
+
+            let o0: Option<u64> = mk().split_gen();
+            let o1: Option<[u64; 2]> = mk().split_gen();
+            assert_eq!(o0.is_some(), o1.is_some());
+
+            let r0: Result<u64, u64> = mk().split_gen();
+            let r1: Result<bool, u64> = mk().split_gen();
+            assert_eq!(r0.is_ok(), r1.is_ok());
+            if let (Err(e0), Err(e1)) = (r0, r1) {
+                assert_eq!(e0, e1);
+            }
+        }
+    }
+
+    /// Test that tuple and array elements come from `call(k)` on a
+    /// PRF split off the generator, for any length.
+    pub fn test_split_rand_indexed<R, F>(mk: F)
+        where R: SplitRng, F: Fn() -> R
+    {
+        let prf = mk().splitn();
+        let elem = |k: u32| -> u64 { prf.call(k).split_gen() };
+
+        let array: [u64; 100] = mk().split_gen();
+        for (k, x) in array.iter().enumerate() {
+            assert_eq!(*x, elem(k as u32));
+        }
+
+        type T16 = (u64, u64, u64, u64, u64, u64, u64, u64,
+                    u64, u64, u64, u64, u64, u64, u64, u64);
+        let tuple: T16 = mk().split_gen();
+        assert_eq!(tuple.0, elem(0));
+        assert_eq!(tuple.12, elem(12));
+        assert_eq!(tuple.15, elem(15));
+
+        let empty: [u64; 0] = mk().split_gen();
+        assert_eq!(empty, [0u64; 0]);
+    }
+
+    /// A binary tree whose size is bounded by its size budget.
+    #[derive(Debug, PartialEq)]
+    enum Tree {
+        Leaf(u64),
+        Node(Box<Tree>, Box<Tree>),
+    }
+
+    impl Tree {
+        fn count(&self) -> usize {
+            match *self {
+                Tree::Leaf(_) => 1,
+                Tree::Node(ref l, ref r) => 1 + l.count() + r.count(),
+            }
+        }
+    }
+
+    impl SplitRand for Tree {
+        fn split_rand<R: SplitRng>(rng: &mut R) -> Tree {
+            rng.split_gen_sized(16)
+        }
+
+        fn split_rand_sized<R: SplitRng>(rng: &mut R, size: usize) -> Tree {
+            let prf = rng.splitn();
+            if size == 0 || prf.call(0).gen_weighted_bool(4) {
+                Tree::Leaf(prf.call(1).gen())
+            } else {
+                let (l, r) = prf.call(2).split_gen_sized((size - 1) / 2);
+                Tree::Node(l, r)
+            }
+        }
+    }
+
+    /// Test that size budgets bound the size of generated values,
+    /// and are passed down without breaking independence.
+    pub fn test_split_rand_sized<R, F>(mk: F)
+        where R: SplitRng, F: Fn() -> R
+    {
+        for i in 0..20 {
+            let mk = || mk().at_path(&[i]);
+
+            let tree: Tree = mk().split_gen_sized(i as usize);
+            assert!(tree.count() <= 2 * i as usize + 1);
+            assert_eq!(tree, mk().split_gen_sized(i as usize));
+
+            let nested: Vec<Vec<Vec<u64>>> = mk().split_gen_sized(i as usize);
+            let total: usize = nested.iter()
+                .map(|v| v.len() + v.iter().map(|w| w.len()).sum::<usize>())
+                .sum::<usize>() + nested.len();
+            assert!(total <= i as usize);
+
+            let (a0, a1): (Vec<u64>, Tree) = mk().split_gen_sized(i as usize);
+            let (b0, b1): (Vec<u64>, [Tree; 2]) = mk().split_gen_sized(i as usize);
+            let (c0, c1): ([Tree; 2], Tree) = mk().split_gen_sized(i as usize);
+            assert_eq!(a0, b0);
+            assert!(a0.len() <= i as usize);
+            assert_eq!(a1, c1);
+            let (d0, d1): ([Tree; 2], [Tree; 2]) = mk().split_gen_sized(i as usize);
+            assert_eq!(b1, d1);
+            assert_eq!(c0, d0);
+
+            let v: Vec<u64> = mk().split_gen_sized(0);
+            assert!(v.is_empty());
+
+            let v: Vec<()> = mk().split_gen_sized(usize::MAX);
+            assert!(v.len() <= MAX_SIZED_LEN as usize);
+        }
+    }
+
     /// Test generation of closures.
     pub fn test_split_rand_closure<R: SplitRng>(rng: &mut R) {
-        type F = Box<Fn([u64; 8]) -> [u64; 8]>;
+        type F = Box<dyn Fn([u64; 8]) -> [u64; 8]>;
 
         let prf = rng.splitn();
         let i = rng.gen();
@@ -475,6 +1467,35 @@ mod tests {
     }
 
 
+    /// Test that `CoSplitRand` steers equal values to the same
+    /// generator, and structurally different ones apart.
+    pub fn test_co_split_rand<R: SplitRng>(rng: &mut R) {
+        fn steer<R, A>(prf: &R::Prf, arg: A) -> u64
+            where R: SplitRng, A: CoSplitRand
+        {
+            arg.co_split_rand(prf.call(0)).gen()
+        }
+
+        let prf = rng.splitn();
+        for _ in 0..20 {
+            let x: f64 = rng.gen();
+            assert_eq!(steer::<R, _>(&prf, x), steer::<R, _>(&prf, x));
+            assert!(steer::<R, _>(&prf, x) != steer::<R, _>(&prf, -x));
+        }
+        assert!(steer::<R, _>(&prf, 0.0f64) != steer::<R, _>(&prf, -0.0f64));
+
+        let distinct = |a: u64, b: u64| assert!(a != b);
+        distinct(steer::<R, _>(&prf, (1u8, 2u8)), steer::<R, _>(&prf, (2u8, 1u8)));
+        distinct(steer::<R, _>(&prf, vec![0u32]), steer::<R, _>(&prf, vec![0u32, 0]));
+        distinct(steer::<R, _>(&prf, (vec![1u8], vec![2u8])),
+                 steer::<R, _>(&prf, (vec![1u8, 2], Vec::<u8>::new())));
+        distinct(steer::<R, _>(&prf, None::<()>), steer::<R, _>(&prf, Some(())));
+        distinct(steer::<R, _>(&prf, Ok::<u8, u8>(0)), steer::<R, _>(&prf, Err::<u8, u8>(0)));
+        distinct(steer::<R, _>(&prf, "ab"), steer::<R, _>(&prf, "a"));
+        distinct(steer::<R, _>(&prf, 1u64 << 40), steer::<R, _>(&prf, 0u64));
+    }
+
+
     /// Test that splitting a generator produces reproducible
     /// sequential results.
     pub fn test_split_rand_split<R: SplitRng>(rng: &mut R) {
@@ -496,6 +1517,40 @@ mod tests {
     }
 
 
+    /// Test that a generator and a PRF taken off it come back from a
+    /// round trip through serialization in the same state, partway
+    /// through the generator's output buffer or not.
+    #[cfg(feature = "serde")]
+    pub fn test_serde_roundtrip<R>(rng: &mut R)
+        where R: SplitRng + Serialize + DeserializeOwned,
+              R::Prf: Serialize + DeserializeOwned
+    {
+        for skip in 0..8 {
+            let mut ra = rng.split();
+            for _ in 0..skip {
+                ra.next_u32();
+            }
+            let json = serde_json::to_string(&ra).expect("serialize");
+            let mut rb: R = serde_json::from_str(&json).expect("deserialize");
+            assert!(iter_eq(ra.gen_iter::<u32>().take(20),
+                            rb.gen_iter::<u32>().take(20)));
+
+            let mut ra1 = ra.split();
+            let mut rb1 = rb.split();
+            assert!(iter_eq(ra1.gen_iter::<u64>().take(20),
+                            rb1.gen_iter::<u64>().take(20)));
+
+            let pa = ra.splitn();
+            let json = serde_json::to_string(&pa).expect("serialize");
+            let pb: R::Prf = serde_json::from_str(&json).expect("deserialize");
+            for i in 0..4 {
+                assert!(iter_eq(pa.call(i).gen_iter::<u64>().take(8),
+                                pb.call(i).gen_iter::<u64>().take(8)));
+            }
+        }
+    }
+
+
     /*
      * The tests below here are lightly adapted from the `rand` crate.
      */
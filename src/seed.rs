@@ -0,0 +1,275 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A seed type that every generator in this crate accepts, so that
+//! generic code can seed whichever generator it was given, and
+//! functions to expand short or human-readable seeds into one or to
+//! draw one from the operating system's entropy source.
+//!
+//! ```
+//! use rand_split::SplitRng;
+//! use rand_split::seed::SeedRng;
+//! use rand_split::siprng::SipRng;
+//! use rand_split::twolcg::TwoLcgRng;
+//!
+//! fn first<R: SeedRng>(seed: u64) -> u64 {
+//!     R::seed_from_u64(seed).split_gen()
+//! }
+//!
+//! assert_eq!(first::<SipRng>(42), first::<SipRng>(42));
+//! assert!(first::<SipRng>(42) != first::<TwoLcgRng>(42));
+//!
+//! let rng = SipRng::from_passphrase("correct horse battery staple");
+//! let rng = SipRng::from_entropy().expect("no entropy source");
+//! ```
+//!
+//! ## Byte layout
+//!
+//! A `Seed` is 32 bytes, read as little-endian words, so a seed
+//! means the same thing on every platform:
+//!
+//! * `SipRng` and `ChaskeyRng` take a 128-bit key, so they hash all 32
+//!   bytes down to one with `seed_to_key`: key word `i` is
+//!   SipHash-2-4 of the seed under the key `(i, 1)`.  `SipRng` uses
+//!   the two words as `k0` and `k1`; `ChaskeyRng` splits each into
+//!   its low and high halves, for four key words.  Every byte of the
+//!   seed affects every bit of the key, so seeds that differ anywhere
+//!   give unrelated generators.
+//! * `TwoLcgRng` reads the bytes as `s1`, `s2`, `g1` and `g2`, like
+//!   its `[u64; 4]` seed.
+//! * A `generic::Split` passes the seed to its splittable generator.
+
+use rand::SeedableRng;
+#[cfg(feature = "std")]
+use rand::{Rng, OsRng};
+#[cfg(feature = "std")]
+use std::io;
+use super::SplitRng;
+
+
+/// The seed that every generator in this crate accepts through
+/// `SeedableRng`.
+pub type Seed = [u8; 32];
+
+/// Splittable generators that accept a `Seed`, with constructors
+/// that expand shorter seeds into one.
+pub trait SeedRng: SplitRng + SeedableRng<Seed> {
+    /// The generator for the seed that `expand_u64` gives for `n`.
+    fn seed_from_u64(n: u64) -> Self {
+        Self::from_seed(expand_u64(n))
+    }
+
+    /// The generator for the seed that `expand_passphrase` gives for
+    /// `passphrase`.
+    fn from_passphrase(passphrase: &str) -> Self {
+        Self::from_seed(expand_passphrase(passphrase.as_bytes()))
+    }
+
+    /// A generator seeded from the operating system's entropy
+    /// source, or the error that opening it gave.
+    #[cfg(feature = "std")]
+    fn from_entropy() -> io::Result<Self> {
+        Ok(Self::from_seed(entropy_seed()?))
+    }
+}
+
+impl<R: SplitRng + SeedableRng<Seed>> SeedRng for R {}
+
+/// Expand a `u64` into a `Seed`: the first four outputs of
+/// SplitMix64 started at `n`, as little-endian bytes.  Nearby values
+/// of `n` give unrelated seeds.
+pub fn expand_u64(n: u64) -> Seed {
+    let mut state = n;
+    words_to_seed(|_| {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// Expand a passphrase into a `Seed`: word `i` is SipHash-2-4 of
+/// the passphrase under the key `(i, 0)`, as little-endian bytes.
+pub fn expand_passphrase(passphrase: &[u8]) -> Seed {
+    words_to_seed(|i| siphash24(i, 0, passphrase))
+}
+
+/// A `Seed` read from the operating system's entropy source.
+#[cfg(feature = "std")]
+pub fn entropy_seed() -> io::Result<Seed> {
+    let mut seed = [0u8; 32];
+    OsRng::new()?.fill_bytes(&mut seed);
+    Ok(seed)
+}
+
+/// Hash a `Seed` down to a 128-bit key: word `i` is SipHash-2-4 of
+/// the seed under the key `(i, 1)`.  (`expand_passphrase` uses the
+/// keys `(i, 0)`, so a passphrase's key isn't one of its seed's
+/// words.)
+pub fn seed_to_key(seed: &Seed) -> [u64; 2] {
+    [siphash24(0, 1, seed), siphash24(1, 1, seed)]
+}
+
+/// Read a `Seed` as four little-endian `u64`s.
+pub fn seed_to_words(seed: &Seed) -> [u64; 4] {
+    let mut result = [0u64; 4];
+    for (word, chunk) in result.iter_mut().zip(seed.chunks(8)) {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(chunk);
+        *word = u64::from_le_bytes(buf);
+    }
+    result
+}
+
+fn words_to_seed<F: FnMut(u64) -> u64>(mut word: F) -> Seed {
+    let mut result = [0u8; 32];
+    for (i, chunk) in result.chunks_mut(8).enumerate() {
+        chunk.copy_from_slice(&word(i as u64).to_le_bytes());
+    }
+    result
+}
+
+/// Plain SipHash-2-4 of `msg` under the key `(k0, k1)`.  This is
+/// kept apart from `SipRng`'s hashing so that passphrases keep
+/// expanding to the same seeds if `SipRng` changes, and so that
+/// `SipRng`'s tests can check it against a straightforward SipHash.
+pub(crate) fn siphash24(k0: u64, k1: u64, msg: &[u8]) -> u64 {
+    let mut v = [k0 ^ 0x736f6d6570736575, k1 ^ 0x646f72616e646f6d,
+                 k0 ^ 0x6c7967656e657261, k1 ^ 0x7465646279746573];
+    let mut chunks = msg.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(chunk);
+        sip_compress(&mut v, u64::from_le_bytes(buf), 2);
+    }
+    let mut last = (msg.len() as u64) << 56;
+    for (i, &byte) in chunks.remainder().iter().enumerate() {
+        last |= (byte as u64) << (8 * i);
+    }
+    sip_compress(&mut v, last, 2);
+
+    v[2] ^= 0xff;
+    sip_compress(&mut v, 0, 4);
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+/// Mix the block `m` into the SipHash state with `rounds` SipRounds.
+fn sip_compress(v: &mut [u64; 4], m: u64, rounds: usize) {
+    v[3] ^= m;
+    for _ in 0..rounds {
+        v[0] = v[0].wrapping_add(v[1]); v[1] = v[1].rotate_left(13);
+        v[1] ^= v[0];                   v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]); v[3] = v[3].rotate_left(16);
+        v[3] ^= v[2];
+        v[0] = v[0].wrapping_add(v[3]); v[3] = v[3].rotate_left(21);
+        v[3] ^= v[0];
+        v[2] = v[2].wrapping_add(v[1]); v[1] = v[1].rotate_left(17);
+        v[1] ^= v[2];                   v[2] = v[2].rotate_left(32);
+    }
+    v[0] ^= m;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chaskeyrng::ChaskeyRng;
+    use generic::Split;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use siprng::SipRng;
+    use twolcg::TwoLcgRng;
+    use super::{Seed, SeedRng, expand_u64, expand_passphrase, seed_to_key, siphash24};
+
+    #[test]
+    fn test_siphash24() {
+        // From the SipHash paper: key 00..0f, message 00..0e.
+        let k0 = 0x0706050403020100;
+        let k1 = 0x0f0e0d0c0b0a0908;
+        let msg: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash24(k0, k1, &msg), 0xa129ca6149be45e5);
+        assert_eq!(siphash24(k0, k1, &[]), 0x726fdb47dd0e0e31);
+    }
+
+    #[test]
+    fn test_expand_u64() {
+        // The first outputs of SplitMix64 from 0.
+        let seed = expand_u64(0);
+        assert_eq!(super::seed_to_words(&seed),
+                   [0xe220a8397b1dcdaf, 0x6e789e6aa1b965f4,
+                    0x06c45d188009454f, 0xf88bb8a8724c81ec]);
+        assert!(expand_u64(1) != seed);
+        assert!(expand_passphrase(b"a") != expand_passphrase(b"b"));
+    }
+
+    #[test]
+    fn test_seed_layout() {
+        let mut seed: Seed = [0; 32];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let [a, b] = seed_to_key(&seed);
+        assert_eq!(a, siphash24(0, 1, &seed));
+        assert_eq!(b, siphash24(1, 1, &seed));
+
+        let mut ra: SipRng = SeedableRng::from_seed(seed);
+        let mut rb = SipRng::new(a, b);
+        assert_eq!(ra.next_u64(), rb.next_u64());
+
+        let mut ra: ChaskeyRng = SeedableRng::from_seed(seed);
+        let mut rb = ChaskeyRng::new([a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32]);
+        assert_eq!(ra.next_u64(), rb.next_u64());
+
+        let mut ra: TwoLcgRng = SeedableRng::from_seed(seed);
+        let mut rb = TwoLcgRng::new(0x0706050403020100, 0x0f0e0d0c0b0a0908,
+                                    0x1716151413121110, 0x1f1e1d1c1b1a1918);
+        assert_eq!(ra.next_u64(), rb.next_u64());
+    }
+
+    #[test]
+    fn test_seed_key_mixes_all_bytes() {
+        // Under an XOR fold, these two seeds would give the same key.
+        let mut s0: Seed = [0; 32];
+        let mut s1: Seed = [0; 32];
+        s0[0] = 1;
+        s1[16] = 1;
+        assert!(seed_to_key(&s0) != seed_to_key(&s1));
+
+        let base = seed_to_key(&[0; 32]);
+        for i in 0..32 {
+            let mut seed: Seed = [0; 32];
+            seed[i] = 0x80;
+            let key = seed_to_key(&seed);
+            assert!(key[0] != base[0] && key[1] != base[1]);
+        }
+    }
+
+    fn test_seed_rng<R: SeedRng>() {
+        let mut ra = R::seed_from_u64(7);
+        let mut rb = R::seed_from_u64(7);
+        let mut rc = R::seed_from_u64(8);
+        let (a, b, c): (u64, u64, u64) = (ra.gen(), rb.gen(), rc.gen());
+        assert_eq!(a, b);
+        assert!(a != c);
+
+        let mut ra = R::from_passphrase("hunter2");
+        let mut rb = R::from_passphrase("hunter2");
+        let mut rc = R::from_passphrase("hunter3");
+        let (a, b, c): (u64, u64, u64) = (ra.gen(), rb.gen(), rc.gen());
+        assert_eq!(a, b);
+        assert!(a != c);
+    }
+
+    #[test]
+    fn test_seed_rngs() {
+        test_seed_rng::<SipRng>();
+        test_seed_rng::<ChaskeyRng>();
+        test_seed_rng::<TwoLcgRng>();
+        test_seed_rng::<Split<SipRng, XorShiftRng>>();
+    }
+}
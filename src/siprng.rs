@@ -35,22 +35,36 @@
 //!   Haskell*, pp. 47-58.
 
 use rand::{Rand, Rng, SeedableRng};
-use super::{SplitRng, SplitPrf};
-use std::mem;
-use std::u32;
+use super::{SplitRng, SplitPrf, Step, Version};
+use seed::{Seed, seed_to_key};
+use core::hash::{Hash, Hasher};
+#[cfg(feature = "trace")]
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 
 /// A splittable pseudorandom generator based on SipHash.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SipRng {
     v0:  u64,
     v1:  u64,
     v2:  u64,
     v3:  u64,
     ctr: u32,
-    len: u8
+    len: u64,
+
+    // Which encoding the trace is in.
+    version: Version,
+
+    // Every block absorbed so far, for `trace_message`.
+    #[cfg(feature = "trace")]
+    trace: Vec<u64>
 }
 
 /// A PRF taken off a `SipRng`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SipPrf(SipRng);
 
 
@@ -65,7 +79,7 @@ macro_rules! sip_round {
         $v2 = $v2.wrapping_add($v1); $v0 = $v0.wrapping_add($v3);
         $v1 = $v1.rotate_left(17);   $v3 = $v3.rotate_left(21);
         $v1 ^= $v2;                  $v3 ^= $v0;
-        $v2 = $v0.rotate_left(32);
+        $v2 = $v2.rotate_left(32);
     }
 }
 
@@ -79,14 +93,16 @@ macro_rules! sip_block {
     }
 }
 
-/// Compute the result of SipHash.  `$len` is the amount of data
-/// hashed, in bytes.
+/// Compute the result of SipHash for a message of `$len` blocks
+/// followed by the seven-byte little-endian encoding of `$len`, when
+/// the `$len` blocks have already been processed.
 macro_rules! sip_finish {
     ($v0: expr, $v1: expr, $v2: expr, $v3: expr, $len: expr) => {
         {
-            sip_block!($v0, $v1, $v2, $v3, 
-                       ($len as u64).wrapping_shl(59));
-            
+            let len = $len as u64;
+            sip_block!($v0, $v1, $v2, $v3,
+                       (len.wrapping_mul(8).wrapping_add(7) << 56) | (len & LEN_MASK));
+
             $v2 ^= 0xff;
             sip_round!($v0, $v1, $v2, $v3);
             sip_round!($v0, $v1, $v2, $v3);
@@ -103,44 +119,151 @@ const C1: u64 = 0x646f72616e646f6d;
 const C2: u64 = 0x6c7967656e657261;
 const C3: u64 = 0x7465646279746573;
 
+/// The number of the latest version of the trace encoding, which
+/// `new` uses.  See the comment on the encoding in the source.
+pub const TRACE_VERSION: u32 = Version::LATEST.number();
+
+/// The tag in the MSBs of a header block.
+const HEADER_TAG: u64 = 0xffff_fff9_0000_0000;
+
+/// The tag in the MSBs of a call block.
+const CALL_TAG: u64 = 0xffff_ffff_0000_0000;
+
+/// The tag in the MSBs of a wide call block.
+const WIDE_TAG: u64 = 0xffff_fffe_0000_0000;
+
+/// The tag in the MSBs of a bytes block.
+const BYTES_TAG: u64 = 0xffff_fffd_0000_0000;
+
+/// The tag in the MSBs of a fold block.
+const FOLD_TAG: u64 = 0xffff_fffc_0000_0000;
+
+/// The tag in the MSBs of a split block.
+const SPLIT_TAG: u64 = 0xffff_fffb_0000_0000;
+
+/// The tag in the MSBs of an overflow block.
+const OVERFLOW_TAG: u64 = 0xffff_fffa_0000_0000;
+
+/// The bits of the block count that go into the final block.
+const LEN_MASK: u64 = 0x00ff_ffff_ffff_ffff;
+
 impl SipRng {
-    /// Create a `SipRng` generator from two `u64`s given as seed.
+    /// Create a `SipRng` generator from two `u64`s given as seed,
+    /// with the latest version of the trace encoding.
     pub fn new(k0: u64, k1: u64) -> SipRng {
-        SipRng { 
+        SipRng::with_version(k0, k1, Version::LATEST)
+    }
+
+    /// Create a `SipRng` generator from two `u64`s given as seed,
+    /// with the given version of the trace encoding.  It and every
+    /// generator split off it keep to that version.
+    pub fn with_version(k0: u64, k1: u64, version: Version) -> SipRng {
+        let mut rng = SipRng {
             v0:  k0 ^ C0,
             v1:  k1 ^ C1,
             v2:  k0 ^ C2,
             v3:  k1 ^ C3,
             ctr: 0,
-            len: 0
+            len: 0,
+            version,
+            #[cfg(feature = "trace")]
+            trace: Vec::new()
+        };
+        match version {
+            Version::V1 => rng.absorb(HEADER_TAG | version.number() as u64),
         }
+        rng
     }
 
-    fn clone(&self) -> SipRng {
-        SipRng { 
-            v0:  self.v0,
-            v1:  self.v1,
-            v2:  self.v2,
-            v3:  self.v3,
-            ctr: self.ctr,
-            len: self.len
-        }
+    /// The generator for `seed` with version 1 of the seed layout and
+    /// the trace encoding.  `new` and `from_seed` follow the latest
+    /// version, which may change in a later release; this gives the
+    /// same stream in every release, as pinned down by
+    /// `tests/golden.rs`.
+    pub fn v1(seed: Seed) -> SipRng {
+        SipRng::from_seed_version(seed, Version::V1)
+    }
+
+    /// The generator for `seed` with the given version.
+    fn from_seed_version(seed: Seed, version: Version) -> SipRng {
+        let [k0, k1] = match version {
+            Version::V1 => seed_to_key(&seed),
+        };
+        SipRng::with_version(k0, k1, version)
+    }
+
+    /// Create the `SipRng` found at `path` under the root generator
+    /// for `seed`.  See `SplitRng::at_path`.
+    pub fn from_seed_and_path<S>(seed: (u64, u64), path: &[S]) -> SipRng
+        where S: Copy + Into<Step>
+    {
+        SipRng::from_seed(seed).at_path(path)
+    }
+
+    /// The message whose SipHash-2-4 is the generator's next output,
+    /// as 64-bit blocks: the trace so far, then the counter block.
+    /// The message ends with the number of these blocks as seven
+    /// little-endian bytes, which aren't included.  See the comment
+    /// on the encoding in the source.
+    ///
+    /// This is for debugging, and needs the `trace` feature, which
+    /// makes every generator keep a copy of its whole trace.
+    #[cfg(feature = "trace")]
+    pub fn trace_message(&self) -> Vec<u64> {
+        let mut message = self.trace.clone();
+        message.push(self.ctr as u64);
+        message
     }
 
 
     /*
-     * The generator works by encoding execution traces as two kinds
-     * of 64-bit blocks that we feed to SipHash:
+     * The generator works by encoding its execution trace as a
+     * message of 64-bit blocks, and each output is SipHash of the
+     * message for the trace so far.  This is version 1 of the
+     * encoding (`Version::V1`).  The header, the counter blocks and
+     * the tagged blocks are written by code that matches on the
+     * generator's `version`, which is where a later version that
+     * changes the encoding gets its own arms, so that generators
+     * built with an older one keep their streams.
+     *
+     * A **counter block** is a u32 counter in the LSBs and all zeroes
+     * in the MSBs.  Every other block has a **tag** in its MSBs:
+     *
+     * * `0xffff_fff9`, a **header block**, with the encoding version
+     *   in its LSBs.  Every trace starts with one.
+     * * `0xffff_fffb`, a **split block**, with 0 in its LSBs for the
+     *   parent's side of a split and 1 for the child's.
+     * * `0xffff_ffff`, a **call block**, with the `u32` index of a
+     *   `SplitPrf::call` in its LSBs.
+     * * `0xffff_fffe`, a **wide call block**, for `call_u64` and
+     *   `call_u128`.  It has the bit width of the index in its LSBs,
+     *   and is followed by the index's words, least significant
+     *   first.
+     * * `0xffff_fffd`, a **bytes block**, for data written through
+     *   the `Hasher` impl.  It's followed by a block with the length
+     *   of the data in bytes, then the data in little-endian,
+     *   zero-padded blocks.
+     * * `0xffff_fffc`, a **fold block**, which `SplitPrf::fold_in`
+     *   writes before the data, so that folding in `a` then `b`
+     *   differs from folding in `(a, b)`.
+     * * `0xffff_fffa`, an **overflow block**.  The counter counts
+     *   outputs; when it would wrap, the generator descends with an
+     *   overflow block instead.
      *
-     * 1. A **counter block**, that records a sequence of `advance`
-     *    operations;
-     * 2. A **split block**, that records a single split operation
-     *    and its branch number.
+     * Each descent writes a counter block with the number of outputs
+     * since the last one, then a tagged block and what follows it.
+     * `split` gives the parent a split block with 0 and the child one
+     * with 1; a PRF is the child's side of a split, and its calls add
+     * a call or wide call block.
      *
-     * A counter block is an u64 value that encodes a u32 counter in
-     * its least significant bits, and all zeroes in the most
-     * significant bits.  A split block is an u64 that encodes an u32
-     * branch number in its LSBs, and all ones in its MSBs.
+     * The message for an output is the trace, then a counter block
+     * with the output's number, then the number of blocks so far as
+     * seven little-endian bytes.  That count won't wrap for any
+     * feasible trace, unlike the length byte that SipHash itself
+     * appends.  Counter blocks and tags can't be confused, and each
+     * tag determines how many blocks follow it, so every message can
+     * be parsed back into one trace: different histories hash
+     * different messages.
      */
 
 
@@ -150,42 +273,138 @@ impl SipRng {
         let result: u64 = {
             // Compute a hash result.  This doesn't mutate the
             // generator state.
-            let (mut v0, mut v1, mut v2, mut v3) = 
+            let (mut v0, mut v1, mut v2, mut v3) =
                 (self.v0, self.v1, self.v2, self.v3);
-            sip_block!(v0, v1, v2, v3, self.ctr as u64);
-            sip_finish!(v0, v1, v2, v3, (self.len + 1).wrapping_mul(8))
+            match self.version {
+                Version::V1 => {
+                    sip_block!(v0, v1, v2, v3, self.ctr as u64);
+                    sip_finish!(v0, v1, v2, v3, self.len + 1)
+                }
+            }
         };
 
-        self.ctr = if self.ctr == u32::MAX {
+        if self.ctr == u32::MAX {
             // We're about to overflow the counter.  We avoid a
-            // cycle by descending into a branch.
-            self.descend(0);
-            0
+            // cycle by descending into a branch of its own.
+            self.descend_tag(OVERFLOW_TAG);
         } else {
-            self.ctr.wrapping_add(1)
-        };
-         
+            self.ctr += 1;
+        }
+
         result
     }
 
+    /// Add one block to the trace.
+    #[inline]
+    fn absorb(&mut self, block: u64) {
+        sip_block!(self.v0, self.v1, self.v2, self.v3, block);
+        self.len += 1;
+        #[cfg(feature = "trace")]
+        self.trace.push(block);
+    }
+
+    /// "Descend" into a branch: record the counter, then a tagged
+    /// block.
+    #[inline]
+    fn descend_tag(&mut self, tag: u64) {
+        match self.version {
+            Version::V1 => {
+                let ctr = self.ctr as u64;
+                self.absorb(ctr);
+                self.absorb(tag);
+            }
+        }
+        self.ctr = 0;
+    }
+
+    /// "Descend" into one side of a split.
+    #[inline]
+    fn descend_split(&mut self, side: u64) {
+        self.descend_tag(SPLIT_TAG | side);
+    }
+
     /// "Descend" into a numbered branch.
     #[inline]
     fn descend(&mut self, i: u32) {
-        sip_block!(self.v0, self.v1, self.v2, self.v3, self.ctr as u64);
-        sip_block!(self.v0, self.v1, self.v2, self.v3, 
-                   (i as u64) | 0xffff_ffff_0000_0000);
-        self.len = self.len.wrapping_add(2);
-        self.ctr = 0;
+        self.descend_tag(CALL_TAG | i as u64);
+    }
+
+    /// "Descend" into a branch with a `u64` number.
+    #[inline]
+    fn descend_u64(&mut self, i: u64) {
+        self.descend_tag(WIDE_TAG | 64);
+        self.absorb(i);
+    }
+
+    /// "Descend" into a branch with a `u128` number.
+    #[inline]
+    fn descend_u128(&mut self, i: u128) {
+        self.descend_tag(WIDE_TAG | 128);
+        self.absorb(i as u64);
+        self.absorb((i >> 64) as u64);
+    }
+
+    /// "Descend" into a branch labelled by a byte string.
+    fn descend_bytes(&mut self, bytes: &[u8]) {
+        self.descend_tag(BYTES_TAG);
+        self.absorb(bytes.len() as u64);
+        for chunk in bytes.chunks(8) {
+            let mut block = 0u64;
+            for (k, &byte) in chunk.iter().enumerate() {
+                block |= (byte as u64) << (8 * k);
+            }
+            self.absorb(block);
+        }
+    }
+
+    /// "Descend" into the branch where `fold_in` data goes.
+    #[inline]
+    fn descend_fold(&mut self) {
+        self.descend_tag(FOLD_TAG);
     }
 
 }
 
+/// Hashing data into a `SipRng` descends into a branch labelled by
+/// that data.  `finish` returns the next `u64` that the generator
+/// would output, without advancing it.
+impl Hasher for SipRng {
+    fn write(&mut self, bytes: &[u8]) {
+        self.descend_bytes(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.clone().advance()
+    }
+
+    hasher_int_methods!{}
+}
+
 impl SplitPrf<SipRng> for SipPrf {
     fn call(&self, i: u32) -> SipRng {
         let mut r = self.0.clone();
         r.descend(i);
         r
     }
+
+    fn call_u64(&self, i: u64) -> SipRng {
+        let mut r = self.0.clone();
+        r.descend_u64(i);
+        r
+    }
+
+    fn call_u128(&self, i: u128) -> SipRng {
+        let mut r = self.0.clone();
+        r.descend_u128(i);
+        r
+    }
+
+    fn fold_in<H: Hash + ?Sized>(&self, data: &H) -> SipRng {
+        let mut r = self.0.clone();
+        r.descend_fold();
+        data.hash(&mut r);
+        r
+    }
 }
 
 impl SplitRng for SipRng {
@@ -193,8 +412,8 @@ impl SplitRng for SipRng {
 
     fn split(&mut self) -> Self {
         let mut child = self.clone();
-        self.descend(0);
-        child.descend(1);
+        self.descend_split(0);
+        child.descend_split(1);
         child
     }
 
@@ -203,6 +422,19 @@ impl SplitRng for SipRng {
         SipPrf(child)
     }
 
+    /// Taking steps directly with `descend` skips the copies that
+    /// `split` and `splitn` would make.
+    fn step(mut self, step: Step) -> Self {
+        match step {
+            Step::Left => self.descend_split(0),
+            Step::Right => self.descend_split(1),
+            Step::Call(i) => { self.descend_split(1); self.descend(i) }
+            Step::CallU64(i) => { self.descend_split(1); self.descend_u64(i) }
+            Step::CallU128(i) => { self.descend_split(1); self.descend_u128(i) }
+        }
+        self
+    }
+
 }
 
 impl Rng for SipRng {
@@ -218,26 +450,14 @@ impl Rng for SipRng {
     
     #[inline]
     fn fill_bytes(&mut self, dest: &mut [u8]) {
-        for chunk in dest.chunks_mut(8) {
-            let block = unsafe {
-                mem::transmute::<u64, [u8; 8]>(self.next_u64())
-            };
-            for i in 0..chunk.len() {
-                chunk[i] = block[i];
-            }
-        }
+        ::fill_bytes_le(self, dest)
     }
 }
 
 impl SeedableRng<(u64, u64)> for SipRng {
     
     fn reseed(&mut self, seed: (u64, u64)) {
-        self.v0 = seed.0 ^ C0;
-        self.v1 = seed.1 ^ C1;
-        self.v2 = seed.0 ^ C2;
-        self.v3 = seed.1 ^ C3;
-        self.len = 0;
-        self.ctr = 0;
+        *self = SipRng::with_version(seed.0, seed.1, self.version);
     }
     
     fn from_seed(seed: (u64, u64)) -> SipRng {
@@ -246,6 +466,18 @@ impl SeedableRng<(u64, u64)> for SipRng {
     }
 }
 
+/// See the `seed` module for the byte layout.
+impl SeedableRng<Seed> for SipRng {
+
+    fn reseed(&mut self, seed: Seed) {
+        *self = SipRng::from_seed_version(seed, self.version);
+    }
+
+    fn from_seed(seed: Seed) -> SipRng {
+        SipRng::from_seed_version(seed, Version::LATEST)
+    }
+}
+
 impl Rand for SipRng {
     fn rand<R: Rng>(other: &mut R) -> SipRng {
         let (k0, k1) = other.gen::<(u64, u64)>();
@@ -256,14 +488,18 @@ impl Rand for SipRng {
 
 #[cfg(test)]
 mod tests {
-    use rand::Rng;
+    use rand::{Rng, SeedableRng};
     use rand::os::OsRng;
+    use seed::SeedRng;
+    use seed::siphash24;
     use siprng::SipRng;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+    use {SplitRng, SplitPrf, Step};
 
 
     fn gen_siprng() -> SipRng {
-        let mut osrng = OsRng::new().ok().expect("Could not create OsRng");
-        osrng.gen()
+        SeedRng::from_entropy().expect("Could not read OS entropy")
     }
 
 
@@ -277,14 +513,140 @@ mod tests {
         ::tests::test_split_rand_closure(&mut gen_siprng());
     }
 
+    #[test]
+    fn test_co_split_rand() {
+        ::tests::test_co_split_rand(&mut gen_siprng());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        ::tests::test_serde_roundtrip(&mut gen_siprng());
+    }
+
     #[test]
     fn test_split_rand_split() {
         ::tests::test_split_rand_split(&mut gen_siprng());
     }
 
+    #[test]
+    fn test_prf_index_domains() {
+        ::tests::test_prf_index_domains(&mut gen_siprng());
+    }
+
+    #[test]
+    fn test_at_path() {
+        let seed = gen_seed();
+        ::tests::test_at_path(|| SipRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_split_rand_collections() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_collections(|| SipRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_split_rand_indexed() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_indexed(|| SipRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_split_rand_sized() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_sized(|| SipRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_fold_in() {
+        ::tests::test_fold_in(&mut gen_siprng());
+    }
+
+    #[test]
+    fn test_hasher() {
+        let seed = gen_seed();
+        let mut ha = SipRng::from_seed(seed);
+        let mut hb = SipRng::from_seed(seed);
+        "some data".hash(&mut ha);
+        "some data".hash(&mut hb);
+        assert_eq!(ha.finish(), hb.finish());
+        assert_eq!(ha.finish(), ha.next_u64());
+        assert!(ha.finish() != hb.finish());
+
+        // Data that straddles block boundaries differently must hash
+        // differently.
+        let mut hc = SipRng::from_seed(seed);
+        let mut hd = SipRng::from_seed(seed);
+        hc.write(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        hd.write(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 0]);
+        assert!(hc.finish() != hd.finish());
+    }
+
+    #[test]
+    fn test_from_seed_and_path() {
+        let seed = gen_seed();
+        let mut ra = SipRng::from_seed_and_path(seed, &[3, 1, 4]);
+        let mut rb = SipRng::from_seed(seed).at_path(&[3, 1, 4]);
+        assert_eq!(ra.next_u64(), rb.next_u64());
+    }
+
+
+    #[test]
+    fn test_trace_tags_distinct() {
+        let seed = gen_seed();
+        let first = |path: &[Step]| SipRng::from_seed(seed).at_path(path).next_u64();
+
+        // Under the old encoding a split and a call shared a tag, so
+        // these two paths gave the same generator.
+        assert!(first(&[Step::Right, Step::Left]) != first(&[Step::Call(0)]));
+        assert!(first(&[Step::Right, Step::Right]) != first(&[Step::Call(1)]));
+        assert!(first(&[Step::Left]) != first(&[Step::Right]));
+    }
+
+    #[test]
+    fn test_counter_exhaustion() {
+        let seed = gen_seed();
+        let mut ra = SipRng::from_seed(seed);
+        ra.ctr = u32::MAX - 1;
+        let mut rb = ra.clone();
+
+        // Run `ra` past the end of its counter, and split `rb` at the
+        // same counter value.
+        ra.next_u64();
+        ra.next_u64();
+        assert_eq!(ra.ctr, 0);
+        rb.next_u64();
+        let mut child = rb.split();
+
+        let xs: [u64; 4] = ra.gen();
+        assert!(xs != rb.gen::<[u64; 4]>());
+        assert!(xs != child.gen::<[u64; 4]>());
+        assert!(xs != SipRng::from_seed(seed).gen::<[u64; 4]>());
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        let seed = gen_seed();
+        let mut rng = SipRng::from_seed(seed);
+        let mut firsts = HashSet::new();
+        for depth in 0..1000u64 {
+            // The header block, then two blocks per step.
+            assert_eq!(rng.len, 1 + 2 * depth);
+            assert!(firsts.insert(rng.clone().next_u64()));
+            rng = rng.step(if depth % 3 == 0 { Step::Right } else { Step::Left });
+        }
+
+        // Paths whose lengths differ by a multiple of 128 steps, where
+        // the old eight-bit length wrapped around.
+        let left = |n: usize| SipRng::from_seed(seed).at_path(&vec![Step::Left; n]);
+        assert!(left(1).next_u64() != left(129).next_u64());
+        assert!(left(128).len > 256);
+    }
+
 
     fn gen_seed() -> (u64, u64) {
-        let mut osrng = OsRng::new().ok().expect("Could not create OsRng");
+        let mut osrng = OsRng::new().expect("Could not create OsRng");
         osrng.gen()
     }
 
@@ -306,4 +668,79 @@ mod tests {
         ::tests::test_rng_reseed::<SipRng, (u64, u64)>(seed);
     }
 
+
+    /// Plain SipHash-2-4, under `seed`, of the message made of
+    /// `blocks` and their count as seven little-endian bytes.
+    fn oracle(seed: (u64, u64), blocks: &[u64]) -> u64 {
+        let mut msg: Vec<u8> = blocks.iter().flat_map(|b| b.to_le_bytes()).collect();
+        msg.extend_from_slice(&(blocks.len() as u64).to_le_bytes()[..7]);
+        siphash24(seed.0, seed.1, &msg)
+    }
+
+    const HEADER: u64 = 0xffff_fff9_0000_0001;
+    const SPLIT: u64 = 0xffff_fffb_0000_0000;
+    const CALL: u64 = 0xffff_ffff_0000_0000;
+
+    /// Output that must be the same on every target.
+    #[test]
+    fn test_portable_output() {
+        ::tests::check_portable_output(
+            SipRng::new(1, 2),
+            [88, 153, 225, 134, 152, 63, 230, 117, 66, 196, 230, 228, 186],
+            (0x767d35ca, -0x6931bef0), (0x3f50992e, 0x3fd4134c86db41f0));
+    }
+
+    #[test]
+    fn test_matches_siphash() {
+        let seed = (0x0706050403020100, 0x0f0e0d0c0b0a0908);
+        let mut rng = SipRng::from_seed(seed);
+        assert_eq!(rng.next_u64(), oracle(seed, &[HEADER, 0]));
+        assert_eq!(rng.next_u64(), oracle(seed, &[HEADER, 1]));
+
+        let mut child = rng.split();
+        assert_eq!(child.next_u64(), oracle(seed, &[HEADER, 2, SPLIT | 1, 0]));
+        assert_eq!(rng.next_u64(), oracle(seed, &[HEADER, 2, SPLIT, 0]));
+
+        let mut at7 = rng.splitn().call(7);
+        assert_eq!(at7.next_u64(),
+                   oracle(seed, &[HEADER, 2, SPLIT, 1, SPLIT | 1, 0, CALL | 7, 0]));
+    }
+
+    /// Check that the next few outputs are the oracle's for the
+    /// generator's `trace_message`.
+    #[cfg(feature = "trace")]
+    fn check_trace(seed: (u64, u64), rng: &mut SipRng) {
+        for _ in 0..3 {
+            let message = rng.trace_message();
+            assert_eq!(rng.next_u64(), oracle(seed, &message));
+        }
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_trace_message() {
+        let seed = gen_seed();
+        let mut rng = SipRng::from_seed(seed);
+        assert_eq!(rng.trace_message(), vec![HEADER, 0]);
+        check_trace(seed, &mut rng);
+
+        let path = [Step::Left, Step::Right, Step::Call(u32::MAX),
+                    Step::CallU64(u64::MAX), Step::CallU128(u128::MAX)];
+        for i in 0..path.len() {
+            check_trace(seed, &mut SipRng::from_seed(seed).at_path(&path[..i + 1]));
+        }
+
+        let mut at3 = SipRng::from_seed(seed).at_path(&[Step::Right, Step::Call(3)]);
+        assert_eq!(at3.trace_message(),
+                   vec![HEADER, 0, SPLIT | 1, 0, SPLIT | 1, 0, CALL | 3, 0]);
+        check_trace(seed, &mut at3);
+
+        let mut folded = rng.splitn().fold_in("abc");
+        check_trace(seed, &mut folded);
+
+        // Past the end of the counter.
+        rng.ctr = u32::MAX - 1;
+        check_trace(seed, &mut rng);
+        assert_eq!(rng.ctr, 1);
+    }
 }
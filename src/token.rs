@@ -0,0 +1,485 @@
+// Copyright 2016 Luis Casillas. See the COPYRIGHT file at the
+// top-level directory of this distribution
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Short text tokens that name a generator by its algorithm, its
+//! root seed and its path down the split tree, so that a generator
+//! deep inside some computation can be logged as one string and
+//! rebuilt exactly from it:
+//!
+//! ```
+//! use rand_split::{SplitRng, Step};
+//! use rand_split::siprng::SipRng;
+//! use rand_split::token::{GeneratorToken, TokenRng};
+//!
+//! let token = SipRng::token((1, 2), &[Step::Call(7), Step::Right]);
+//! let logged = token.to_string();
+//! assert_eq!(logged, "siprng:aearaaiaaaaaaaaaaabaaaaaaaaaaaaca4aq");
+//!
+//! let parsed: GeneratorToken = logged.parse().unwrap();
+//! let mut rng: SipRng = parsed.generator().unwrap();
+//! let mut expected = SipRng::from_seed_and_path((1, 2), &[Step::Call(7), Step::Right]);
+//! assert_eq!(rng.split_gen::<u64>(), expected.split_gen::<u64>());
+//! ```
+//!
+//! ## Format
+//!
+//! A token is the generator's algorithm name, a colon, and a payload
+//! in unpadded lowercase base32 (the RFC 4648 alphabet).  The
+//! algorithm names are `siprng` and `chaskey`; a `generic::Split`
+//! over a generator with name `name` is `split/name`.  The payload's
+//! bytes are:
+//!
+//! 1. The format version, currently 1;
+//! 2. The number of the generator's encoding `Version`, so that the
+//!    token rebuilds the same generator in later releases;
+//! 3. The length of the seed as an unsigned LEB128 number, and the
+//!    seed itself, in the layout that
+//!    the algorithm's `TokenRng::encode_seed` gives;
+//! 4. The steps of the path, each a tag byte followed, for the
+//!    `Call` steps, by the index as an unsigned LEB128 number.  The
+//!    tags are 0 for `Left`, 1 for `Right`, 2 for `Call`, 3 for
+//!    `CallU64` and 4 for `CallU128`.
+//!
+//! A `generic::Split`'s token doesn't record its sequential
+//! generator's type, so it must be parsed back into a `Split` with
+//! the same one.
+
+use chaskeyrng::ChaskeyRng;
+use generic::Split;
+use rand::{Rand, Rng};
+use siprng::SipRng;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::error::Error;
+use super::{SplitRng, Step, Version};
+
+
+/// A generator's algorithm name, encoding version, root seed and
+/// path down the split tree.  `Display` and `FromStr` convert it to
+/// and from its text form.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GeneratorToken {
+    /// The name of the generator's algorithm, like `siprng`.
+    pub algorithm: String,
+    /// The encoding version of the root generator.
+    pub version: Version,
+    /// The root seed, encoded by the algorithm's `encode_seed`.
+    pub seed: Vec<u8>,
+    /// The path from the root generator to this one.
+    pub path: Vec<Step>,
+}
+
+impl GeneratorToken {
+    /// The token of the generator one `step` below this one.
+    pub fn child(&self, step: Step) -> GeneratorToken {
+        let mut result = self.clone();
+        result.path.push(step);
+        result
+    }
+
+    /// Rebuild the generator that this token names.
+    pub fn generator<G: TokenRng>(&self) -> Result<G, TokenError> {
+        G::from_token(self)
+    }
+}
+
+/// What can go wrong parsing a token or rebuilding its generator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenError {
+    /// The text isn't of the form `algorithm:payload`, or the
+    /// payload isn't base32.
+    Syntax,
+    /// The payload is of a format version this crate doesn't know.
+    Version(u8),
+    /// The payload names an encoding `Version` this crate doesn't
+    /// know.
+    GeneratorVersion(u8),
+    /// The payload ends in the middle of a seed or a step, or a
+    /// step's index is out of range.
+    Payload,
+    /// The token is for a different algorithm than the generator
+    /// type it's being rebuilt as.
+    Algorithm { expected: String, found: String },
+    /// The seed is the wrong size for the algorithm.
+    Seed,
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TokenError::Syntax => f.write_str("malformed generator token"),
+            TokenError::Version(v) => write!(f, "unknown generator token version {}", v),
+            TokenError::GeneratorVersion(v) =>
+                write!(f, "unknown generator encoding version {}", v),
+            TokenError::Payload => f.write_str("truncated or invalid generator token payload"),
+            TokenError::Algorithm { ref expected, ref found } =>
+                write!(f, "expected a {} generator token, found {}", expected, found),
+            TokenError::Seed => f.write_str("wrong seed size in generator token"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for TokenError {}
+
+
+/// Splittable generators that can be named by a `GeneratorToken`.
+pub trait TokenRng: SplitRng {
+    /// The type of the generator's root seed.
+    type Seed;
+
+    /// The algorithm's name in tokens.
+    fn algorithm() -> String;
+
+    /// The seed's bytes in tokens.
+    fn encode_seed(seed: &Self::Seed) -> Vec<u8>;
+
+    /// The seed whose bytes are `bytes`, or `None` if they're the
+    /// wrong size.
+    fn decode_seed(bytes: &[u8]) -> Option<Self::Seed>;
+
+    /// The root generator for `seed`, of the given encoding version.
+    fn root(seed: Self::Seed, version: Version) -> Self;
+
+    /// The token for the generator at `path` under the root
+    /// generator for `seed`, of the latest encoding version.
+    fn token<S>(seed: Self::Seed, path: &[S]) -> GeneratorToken
+        where S: Copy + Into<Step>
+    {
+        GeneratorToken {
+            algorithm: Self::algorithm(),
+            version: Version::LATEST,
+            seed: Self::encode_seed(&seed),
+            path: path.iter().map(|&step| step.into()).collect(),
+        }
+    }
+
+    /// Rebuild the generator that `token` names.
+    fn from_token(token: &GeneratorToken) -> Result<Self, TokenError> {
+        let expected = Self::algorithm();
+        if token.algorithm != expected {
+            return Err(TokenError::Algorithm {
+                expected,
+                found: token.algorithm.clone(),
+            });
+        }
+        let seed = Self::decode_seed(&token.seed).ok_or(TokenError::Seed)?;
+        Ok(Self::root(seed, token.version).at_path(&token.path))
+    }
+}
+
+/// The seed is `k0` then `k1`, as little-endian `u64`s.
+impl TokenRng for SipRng {
+    type Seed = (u64, u64);
+
+    fn algorithm() -> String {
+        "siprng".to_string()
+    }
+
+    fn encode_seed(seed: &(u64, u64)) -> Vec<u8> {
+        let mut result = Vec::with_capacity(16);
+        result.extend_from_slice(&seed.0.to_le_bytes());
+        result.extend_from_slice(&seed.1.to_le_bytes());
+        result
+    }
+
+    fn decode_seed(bytes: &[u8]) -> Option<(u64, u64)> {
+        if bytes.len() != 16 {
+            return None;
+        }
+        let word = |i: usize| {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&bytes[8 * i..8 * i + 8]);
+            u64::from_le_bytes(buf)
+        };
+        Some((word(0), word(1)))
+    }
+
+    fn root(seed: (u64, u64), version: Version) -> SipRng {
+        SipRng::with_version(seed.0, seed.1, version)
+    }
+}
+
+/// The seed is its four words, as little-endian `u32`s.
+impl TokenRng for ChaskeyRng {
+    type Seed = [u32; 4];
+
+    fn algorithm() -> String {
+        "chaskey".to_string()
+    }
+
+    fn encode_seed(seed: &[u32; 4]) -> Vec<u8> {
+        seed.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    fn decode_seed(bytes: &[u8]) -> Option<[u32; 4]> {
+        if bytes.len() != 16 {
+            return None;
+        }
+        let mut result = [0u32; 4];
+        for (word, chunk) in result.iter_mut().zip(bytes.chunks(4)) {
+            let mut buf = [0u8; 4];
+            buf.copy_from_slice(chunk);
+            *word = u32::from_le_bytes(buf);
+        }
+        Some(result)
+    }
+
+    fn root(seed: [u32; 4], version: Version) -> ChaskeyRng {
+        ChaskeyRng::with_version(seed, version)
+    }
+}
+
+/// The seed is the splittable generator's.
+impl<S, R> TokenRng for Split<S, R>
+    where S: TokenRng,
+          R: Rng + Rand
+{
+    type Seed = S::Seed;
+
+    fn algorithm() -> String {
+        format!("split/{}", S::algorithm())
+    }
+
+    fn encode_seed(seed: &S::Seed) -> Vec<u8> {
+        S::encode_seed(seed)
+    }
+
+    fn decode_seed(bytes: &[u8]) -> Option<S::Seed> {
+        S::decode_seed(bytes)
+    }
+
+    fn root(seed: S::Seed, version: Version) -> Split<S, R> {
+        Split::wrap(S::root(seed, version))
+    }
+}
+
+
+/*
+ * The text format.
+ */
+
+const VERSION: u8 = 1;
+
+const BASE32: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+impl Display for GeneratorToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut payload = vec![VERSION, self.version.number() as u8];
+        write_leb128(&mut payload, self.seed.len() as u128);
+        payload.extend_from_slice(&self.seed);
+        for step in &self.path {
+            match *step {
+                Step::Left => payload.push(0),
+                Step::Right => payload.push(1),
+                Step::Call(i) => { payload.push(2); write_leb128(&mut payload, i as u128) }
+                Step::CallU64(i) => { payload.push(3); write_leb128(&mut payload, i as u128) }
+                Step::CallU128(i) => { payload.push(4); write_leb128(&mut payload, i) }
+            }
+        }
+        write!(f, "{}:{}", self.algorithm, encode_base32(&payload))
+    }
+}
+
+impl FromStr for GeneratorToken {
+    type Err = TokenError;
+
+    fn from_str(s: &str) -> Result<GeneratorToken, TokenError> {
+        let colon = s.rfind(':').ok_or(TokenError::Syntax)?;
+        let (algorithm, encoded) = (&s[..colon], &s[colon + 1..]);
+        if algorithm.is_empty() {
+            return Err(TokenError::Syntax);
+        }
+        let payload = decode_base32(encoded).ok_or(TokenError::Syntax)?;
+
+        let mut bytes = payload.iter().cloned();
+        match bytes.next() {
+            Some(VERSION) => {}
+            Some(version) => return Err(TokenError::Version(version)),
+            None => return Err(TokenError::Payload),
+        }
+        let number = bytes.next().ok_or(TokenError::Payload)?;
+        let version = Version::from_number(number as u32)
+            .ok_or(TokenError::GeneratorVersion(number))?;
+        let seed_len = read_index(&mut bytes, 32)? as usize;
+        let seed: Vec<u8> = bytes.by_ref().take(seed_len).collect();
+        if seed.len() != seed_len {
+            return Err(TokenError::Payload);
+        }
+
+        let mut path = Vec::new();
+        while let Some(tag) = bytes.next() {
+            path.push(match tag {
+                0 => Step::Left,
+                1 => Step::Right,
+                2 => Step::Call(read_index(&mut bytes, 32)? as u32),
+                3 => Step::CallU64(read_index(&mut bytes, 64)? as u64),
+                4 => Step::CallU128(read_index(&mut bytes, 128)?),
+                _ => return Err(TokenError::Payload),
+            });
+        }
+        Ok(GeneratorToken { algorithm: algorithm.to_string(), version, seed, path })
+    }
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut n: u128) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Read an unsigned LEB128 number that fits in `bits` bits.
+fn read_index<I: Iterator<Item=u8>>(bytes: &mut I, bits: u32) -> Result<u128, TokenError> {
+    let mut result = 0u128;
+    let mut shift = 0;
+    loop {
+        let byte = bytes.next().ok_or(TokenError::Payload)?;
+        let low = (byte & 0x7f) as u128;
+        if shift >= bits || (shift + 7 > bits && low >> (bits - shift) != 0) {
+            return Err(TokenError::Payload);
+        }
+        result |= low << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 8 / 5 + 1);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        result.push(BASE32[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    result
+}
+
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = BASE32.iter().position(|&d| d == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+        }
+    }
+    // Leftover bits must be padding, and zero.
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chaskeyrng::ChaskeyRng;
+    use generic::Split;
+    use rand::{Rng, XorShiftRng};
+    use rand::os::OsRng;
+    use siprng::SipRng;
+    use super::{GeneratorToken, TokenError, TokenRng};
+    use ::{SplitRng, Step, Version};
+
+    fn gen_path<R: Rng>(rng: &mut R) -> Vec<Step> {
+        (0..rng.gen_range(0, 12)).map(|_| match rng.gen_range(0, 5) {
+            0 => Step::Left,
+            1 => Step::Right,
+            2 => Step::Call(rng.gen()),
+            3 => Step::CallU64(rng.gen()),
+            _ => Step::CallU128(((rng.gen::<u64>() as u128) << 64) | rng.gen::<u64>() as u128),
+        }).collect()
+    }
+
+    fn test_roundtrip<G: TokenRng>(seed: G::Seed, path: &[Step]) {
+        let token = G::token(seed, path);
+        let parsed: GeneratorToken = token.to_string().parse().expect("parse");
+        assert_eq!(parsed, token);
+
+        let mut ra: G = parsed.generator().expect("generator");
+        let mut rb = G::from_token(&token).expect("from_token");
+        let mut rc = G::root(G::decode_seed(&token.seed).unwrap(), Version::LATEST).at_path(path);
+        let (a, b, c): (u64, u64, u64) = (ra.gen(), rb.gen(), rc.gen());
+        assert_eq!(a, c);
+        assert_eq!(b, c);
+    }
+
+    #[test]
+    fn test_token_roundtrip() {
+        let mut osrng = OsRng::new().expect("Could not create OsRng");
+        for _ in 0..100 {
+            let path = gen_path(&mut osrng);
+            test_roundtrip::<SipRng>(osrng.gen(), &path);
+            test_roundtrip::<ChaskeyRng>(osrng.gen(), &path);
+            test_roundtrip::<Split<SipRng, XorShiftRng>>(osrng.gen(), &path);
+        }
+    }
+
+    #[test]
+    fn test_token_child() {
+        let token = ChaskeyRng::token([1, 2, 3, 4], &[Step::Call(5)]);
+        let mut child: ChaskeyRng = token.child(Step::Left).generator().unwrap();
+        let mut expected = ChaskeyRng::new([1, 2, 3, 4]).at_path(&[Step::Call(5), Step::Left]);
+        assert_eq!(child.next_u64(), expected.next_u64());
+    }
+
+    #[test]
+    fn test_token_errors() {
+        let token = SipRng::token((1, 2), &[3u32]).to_string();
+        assert_eq!(token, "siprng:aearaaiaaaaaaaaaaabaaaaaaaaaaaacam");
+
+        let wrong = ChaskeyRng::from_token(&token.parse().unwrap());
+        assert_eq!(wrong.err(), Some(TokenError::Algorithm {
+            expected: "chaskey".to_string(),
+            found: "siprng".to_string(),
+        }));
+        let split: Result<Split<SipRng, XorShiftRng>, _> = token.parse::<GeneratorToken>()
+            .unwrap().generator();
+        assert!(split.is_err());
+
+        let parse = |s: &str| s.parse::<GeneratorToken>().err();
+        assert_eq!(parse("siprng"), Some(TokenError::Syntax));
+        assert_eq!(parse(":aeaa"), Some(TokenError::Syntax));
+        assert_eq!(parse("siprng:aearaaiaaaaaaaaaaabaaaaaaaaaaaaca1"), Some(TokenError::Syntax));
+        assert_eq!(parse("siprng:ai"), Some(TokenError::Version(2)));
+        assert_eq!(parse("siprng:ae"), Some(TokenError::Payload));
+        assert_eq!(parse("siprng:aeeqa"), Some(TokenError::GeneratorVersion(9)));
+        assert_eq!(parse("siprng:aearaaaa"), Some(TokenError::Payload));
+        // A `Call` index that doesn't fit in a `u32`.
+        assert_eq!(parse("siprng:aeaqaauaqcaiaea"), Some(TokenError::Payload));
+
+        let short = GeneratorToken { seed: vec![0; 8], ..token.parse().unwrap() };
+        assert_eq!(SipRng::from_token(&short).err(), Some(TokenError::Seed));
+
+        // A seed whose length takes two bytes.
+        let long = GeneratorToken { seed: vec![7; 300], ..short };
+        assert_eq!(long.to_string().parse(), Ok(long));
+    }
+}
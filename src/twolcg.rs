@@ -7,12 +7,18 @@
 //!   Generators."  Slide set at: http://on-demand.gputechconf.com/gtc/2016/presentation/s6665-guy-steele-fast-splittable.pdf
 
 use rand::{Rand, Rng, SeedableRng};
-use super::{SplitRng, SplitPrf};
-use std::num::Wrapping;
+use super::{SplitRng, SplitPrf, Version};
+use seed::{Seed, seed_to_words};
+use siprng::SipRng;
+use core::hash::Hash;
+use core::num::Wrapping;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 
 /// A splittable pseudorandom generator based on the TwoLCG algorithm.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TwoLcgRng {
     // The state of the generator (mutable, two words)
     s1: Wrapping<u64>,
@@ -20,27 +26,107 @@ pub struct TwoLcgRng {
 
     // The parameter of the generator (immutable)
     g1: Wrapping<u64>,
-    g2: Wrapping<u64>
+    g2: Wrapping<u64>,
+
+    // How the generator splits
+    version: Version
 }
 
 
 /// A PRF taken off a `TwoLcgRng`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TwoLcgPrf{
-    m: Wrapping<u64>
+    m: Wrapping<u64>,
+    version: Version
 }
 
 
 impl TwoLcgRng {
-    /// Create a new `TwoLcgRng` from the given state.  Note that the
-    /// least significant bit of noth `g1` and `g2` are ignored.
+    /// Create a new `TwoLcgRng` from the given state, splitting as
+    /// the latest version does.  Note that the least significant bit
+    /// of both `g1` and `g2` are ignored.
     pub fn new(s1: u64, s2: u64, g1: u64, g2: u64) -> TwoLcgRng {
+        TwoLcgRng::with_version(s1, s2, g1, g2, Version::LATEST)
+    }
+
+    /// Create a new `TwoLcgRng` from the given state, splitting as
+    /// the given version does.  It and every generator split off it
+    /// keep to that version.
+    pub fn with_version(s1: u64, s2: u64, g1: u64, g2: u64, version: Version) -> TwoLcgRng {
         TwoLcgRng {
             s1: Wrapping(s1),
             s2: Wrapping(s2),
             g1: Wrapping(g1 | 1u64),
-            g2: Wrapping(g2 | 1u64)
+            g2: Wrapping(g2 | 1u64),
+            version
         }
     }
+
+    /// The generator for `seed`, splitting as version 1 does (Steele's
+    /// gamma mixing for `split`, and the PRF children described in
+    /// the source).  `new` and `from_seed` follow the latest version,
+    /// which may change in a later release; this gives the same
+    /// stream in every release, as pinned down by `tests/golden.rs`.
+    pub fn v1(seed: Seed) -> TwoLcgRng {
+        TwoLcgRng::from_words(seed_to_words(&seed), Version::V1)
+    }
+
+    /// The generator whose state is `words`, with the given version.
+    fn from_words(words: [u64; 4], version: Version) -> TwoLcgRng {
+        TwoLcgRng::with_version(words[0], words[1], words[2], words[3], version)
+    }
+
+    /// Seed a new generator of the given version off `rng`.  Version
+    /// 1 does it the way Steele recommends for `split`: the states are
+    /// taken as is, and the gammas are mixed so that they're never
+    /// weak (see `mix_gamma`).
+    fn spawn_from<R: Rng>(rng: &mut R, version: Version) -> TwoLcgRng {
+        match version {
+            Version::V1 => {
+                let (s1, s2) = (rng.next_u64(), rng.next_u64());
+                let (g1, g2) = (mix_gamma(rng.next_u64()), mix_gamma(rng.next_u64()));
+                TwoLcgRng::with_version(s1, s2, g1, g2, version)
+            }
+        }
+    }
+}
+
+/// The MurmurHash3 finalizer, a bijection that scrambles every bit
+/// of `z` into every bit of the result.
+fn mix64(z: u64) -> u64 {
+    let z = (z ^ (z >> 33)).wrapping_mul(0xff51afd7ed558ccd);
+    let z = (z ^ (z >> 33)).wrapping_mul(0xc4ceb9fe1a85ec53);
+    z ^ (z >> 33)
+}
+
+/// Whether a gamma has fewer than 24 bit transitions, which makes for
+/// a poor generator.
+fn is_weak_gamma(g: u64) -> bool {
+    (g ^ (g >> 1)).count_ones() < 24
+}
+
+/// Turn an arbitrary word into a gamma, as in Steele's
+/// `SplittableRandom`: scramble it with `mix64`, force it odd, and if
+/// it's weak flip every other bit.
+fn mix_gamma(z: u64) -> u64 {
+    let z = mix64(z) | 1;
+    if is_weak_gamma(z) {
+        z ^ 0xaaaaaaaaaaaaaaaa
+    } else {
+        z
+    }
+}
+
+/// Turn a scrambled word into a gamma whose low two bits are `tag`
+/// (which must be odd), flipping every other bit above those if it's
+/// weak, like `mix_gamma`.
+fn tag_gamma(z: u64, tag: u64) -> u64 {
+    let g = (z << 2) | tag;
+    if is_weak_gamma(g) {
+        g ^ 0xaaaaaaaaaaaaaaa8
+    } else {
+        g
+    }
 }
 
 impl Rng for TwoLcgRng {
@@ -64,79 +150,141 @@ impl Rng for TwoLcgRng {
         self.s2 = self.s2 * C2 + self.g2;
         (r ^ (r >> 32)).0
     }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        ::fill_bytes_le(self, dest)
+    }
 }
 
 impl SplitRng for TwoLcgRng {
     type Prf = TwoLcgPrf;
 
     fn split(&mut self) -> Self {
-        TwoLcgRng::from_seed(self.gen())
+        let version = self.version;
+        TwoLcgRng::spawn_from(self, version)
     }
 
     fn splitn(&mut self) -> TwoLcgPrf {
+        let m = match self.version {
+            Version::V1 => mix_gamma(self.next_u64()),
+        };
         TwoLcgPrf {
-            m: Wrapping(self.next_u64() | 1)
+            m: Wrapping(m),
+            version: self.version
+        }
+    }
+}
+
+/*
+ * The PRF's children are built the same way for all three index
+ * widths.  The index is offset by one and multiplied by the odd `m`,
+ * one 64-bit word `x` (and a second one `y`, for `u128` indices) at a
+ * time.  Two Feistel rounds of `mix64` turn `(x, y)` into the states,
+ * so both states depend on both words and the pair of them still
+ * determines the index; the gammas are `mix64` of the states, shifted
+ * up past a two-bit tag, with weak ones fixed as in `mix_gamma`.
+ * Every `mix64` gets its own additive salt per index width, so a
+ * state is only zero at an unremarkable index or two per PRF, not at
+ * 0 or at the end of the range.
+ *
+ * The index widths are kept apart by the tags: `call` gives gammas
+ * whose low bits are 01 and 01, `call_u64` 11 and 01, and
+ * `call_u128` 01 and 11.
+ */
+
+const GOLDEN: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl TwoLcgPrf {
+    /// The child for the index words `x` and `y`, already offset, of
+    /// the index width numbered `width`, with gamma tags `tags`.
+    fn child(&self, x: u64, y: u64, width: u64, tags: (u64, u64)) -> TwoLcgRng {
+        match self.version {
+            Version::V1 => {
+                let (x, y) = ((Wrapping(x) * self.m).0, (Wrapping(y) * self.m).0);
+                let salt = |j: u64| GOLDEN.wrapping_mul(4 * width + j + 1);
+                let s2 = mix64(y.wrapping_add(mix64(x.wrapping_add(salt(0)))));
+                let s1 = mix64(x.wrapping_add(mix64(s2.wrapping_add(salt(1)))));
+                TwoLcgRng::with_version(s1, s2,
+                                        tag_gamma(mix64(s1.wrapping_add(salt(2))), tags.0),
+                                        tag_gamma(mix64(s2.wrapping_add(salt(3))), tags.1),
+                                        self.version)
+            }
         }
     }
 }
 
 impl SplitPrf<TwoLcgRng> for TwoLcgPrf {
     fn call(&self, k: u32) -> TwoLcgRng {
-        /*
-         * The construction in here is the one that Steele recommends
-         * in the context of how to create multiple TwoLCG generators
-         * upfront for a set of threads.
-         */
-        const FOUR: Wrapping<u64> = Wrapping(4u64);
-        let (k0, k1, k2, k3) = (
-            Wrapping(k     as u64),
-            Wrapping((k+1) as u64),
-            Wrapping((k+2) as u64),
-            Wrapping((k+3) as u64),
-        );
-        TwoLcgRng::new((FOUR * k0 * self.m).0, 
-                       (FOUR * k2 * self.m).0,
-                       (FOUR * k1 * self.m).0,
-                       (FOUR * k3 * self.m).0)
+        let x = k as u64 + 1;
+        self.child(x, x, 0, (1, 1))
+    }
+
+    fn call_u64(&self, k: u64) -> TwoLcgRng {
+        let x = k.wrapping_add(1);
+        self.child(x, x, 1, (3, 1))
+    }
+
+    fn call_u128(&self, k: u128) -> TwoLcgRng {
+        let (lo, hi) = (k as u64, (k >> 64) as u64);
+        self.child(lo.wrapping_add(1), hi.wrapping_add(1), 2, (1, 3))
+    }
+
+    /// TwoLCG has no hash function of its own to fold data in with,
+    /// so we borrow `SipRng`'s, keyed with the multiplier, and seed
+    /// the result from that.
+    fn fold_in<H: Hash + ?Sized>(&self, data: &H) -> TwoLcgRng {
+        match self.version {
+            Version::V1 => {
+                let mut rng = SipRng::with_version(self.m.0, 0, Version::V1)
+                    .splitn().fold_in(data);
+                TwoLcgRng::spawn_from(&mut rng, self.version)
+            }
+        }
     }
 }
 
 impl SeedableRng<[u64; 4]> for TwoLcgRng {
     
     fn reseed(&mut self, seed: [u64; 4]) {
-        self.s1 = Wrapping(seed[0]);
-        self.s2 = Wrapping(seed[1]);
-        self.g1 = Wrapping(seed[2] | 1);
-        self.g2 = Wrapping(seed[3] | 1);
+        *self = TwoLcgRng::from_words(seed, self.version);
     }
     
     fn from_seed(seed: [u64; 4]) -> TwoLcgRng {
-        TwoLcgRng {
-            s1: Wrapping(seed[0]),
-            s2: Wrapping(seed[1]),
-            g1: Wrapping(seed[2] | 1u64),
-            g2: Wrapping(seed[3] | 1u64)
-        }
+        TwoLcgRng::from_words(seed, Version::LATEST)
+    }
+}
+
+/// See the `seed` module for the byte layout.
+impl SeedableRng<Seed> for TwoLcgRng {
+
+    fn reseed(&mut self, seed: Seed) {
+        self.reseed(seed_to_words(&seed));
+    }
+
+    fn from_seed(seed: Seed) -> TwoLcgRng {
+        TwoLcgRng::from_words(seed_to_words(&seed), Version::LATEST)
     }
 }
 
 impl Rand for TwoLcgRng {
     fn rand<R: Rng>(other: &mut R) -> TwoLcgRng {
-        TwoLcgRng::from_seed(other.gen::<[u64; 4]>())
+        TwoLcgRng::spawn_from(other, Version::LATEST)
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use rand::Rng;
+    use rand::{Rng, SeedableRng};
     use rand::os::OsRng;
-    use twolcg::TwoLcgRng;
+    use seed::SeedRng;
+    use std::collections::HashSet;
+    use twolcg::{TwoLcgRng, mix_gamma, is_weak_gamma};
+    use ::{SplitRng, SplitPrf};
 
 
     fn gen_twolcg() -> TwoLcgRng {
-        let mut osrng = OsRng::new().ok().expect("Could not create OsRng");
-        osrng.gen()
+        SeedRng::from_entropy().expect("Could not read OS entropy")
     }
 
 
@@ -150,14 +298,59 @@ mod tests {
         ::tests::test_split_rand_closure(&mut gen_twolcg());
     }
 
+    #[test]
+    fn test_co_split_rand() {
+        ::tests::test_co_split_rand(&mut gen_twolcg());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        ::tests::test_serde_roundtrip(&mut gen_twolcg());
+    }
+
     #[test]
     fn test_split_rand_split() {
         ::tests::test_split_rand_split(&mut gen_twolcg());
     }
 
+    #[test]
+    fn test_prf_index_domains() {
+        ::tests::test_prf_index_domains(&mut gen_twolcg());
+    }
+
+    #[test]
+    fn test_at_path() {
+        let seed = gen_seed();
+        ::tests::test_at_path(|| TwoLcgRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_split_rand_collections() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_collections(|| TwoLcgRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_split_rand_indexed() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_indexed(|| TwoLcgRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_split_rand_sized() {
+        let seed = gen_seed();
+        ::tests::test_split_rand_sized(|| TwoLcgRng::from_seed(seed));
+    }
+
+    #[test]
+    fn test_fold_in() {
+        ::tests::test_fold_in(&mut gen_twolcg());
+    }
+
 
     fn gen_seed() -> [u64; 4] {
-        let mut osrng = OsRng::new().ok().expect("Could not create OsRng");
+        let mut osrng = OsRng::new().expect("Could not create OsRng");
         osrng.gen()
     }
 
@@ -178,4 +371,61 @@ mod tests {
         let seed = gen_seed();
         ::tests::test_rng_reseed::<TwoLcgRng, [u64; 4]>(seed);
     }
+
+    /// Output that must be the same on every target.
+    #[test]
+    fn test_portable_output() {
+        ::tests::check_portable_output(
+            TwoLcgRng::new(1, 2, 3, 4),
+            [5, 124, 33, 7, 63, 198, 248, 153, 108, 169, 209, 142, 205],
+            (0x8c2c0910, -0x12749083), (0x3ee86974, 0x3fd5a9df68bc1268));
+    }
+
+    #[test]
+    fn test_mix_gamma() {
+        for &z in &[0, 1, 2, 0xaaaaaaaaaaaaaaaa, u64::MAX, 1 << 63] {
+            let g = mix_gamma(z);
+            assert_eq!(g & 1, 1);
+            assert!(!is_weak_gamma(g));
+        }
+    }
+
+    #[test]
+    fn test_split_gammas() {
+        // Gamma 1 is as weak as it gets; its children shouldn't be.
+        let mut rng = TwoLcgRng::new(0, 0, 1, 1);
+        for _ in 0..1000 {
+            let child = rng.split();
+            assert!(!is_weak_gamma(child.g1.0));
+            assert!(!is_weak_gamma(child.g2.0));
+        }
+        let prf = rng.splitn();
+        assert!(!is_weak_gamma(prf.m.0));
+    }
+
+    #[test]
+    fn test_call_edge_indices() {
+        let mut rng = gen_twolcg();
+        let prf = rng.splitn();
+        let edges = [0, 1, 2, 3, 4, u32::MAX / 2, u32::MAX - 4, u32::MAX - 3,
+                     u32::MAX - 2, u32::MAX - 1, u32::MAX];
+        let edges_u64 = [0, 1, u32::MAX as u64, 1 << 32, u64::MAX - 1, u64::MAX];
+        let edges_u128 = [0, 1, 1 << 64, 5 << 64, (1 << 64) + 5, u64::MAX as u128,
+                          u128::MAX - 1, u128::MAX];
+
+        let children = edges.iter().map(|&k| (prf.call(k), (1, 1)))
+            .chain(edges_u64.iter().map(|&k| (prf.call_u64(k), (3, 1))))
+            .chain(edges_u128.iter().map(|&k| (prf.call_u128(k), (1, 3))));
+        let mut states = HashSet::new();
+        let mut outputs = HashSet::new();
+        for (mut child, (tag1, tag2)) in children {
+            assert!(child.s1.0 != 0 && child.s2.0 != 0);
+            assert_eq!(child.g1.0 & 3, tag1);
+            assert_eq!(child.g2.0 & 3, tag2);
+            assert!(!is_weak_gamma(child.g1.0) && !is_weak_gamma(child.g2.0));
+            assert!(states.insert(child.s1.0));
+            assert!(states.insert(child.s2.0));
+            assert!(outputs.insert(child.next_u64()));
+        }
+    }
 }
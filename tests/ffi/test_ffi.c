@@ -0,0 +1,118 @@
+/*
+ * Checks the C interface against outputs computed with the Rust API.
+ * The expected values are also checked on the Rust side, by
+ * `ffi::tests::test_c_known_answers`; keep the two in sync.
+ *
+ * Build and run with `make -C tests/ffi`.
+ */
+
+#include <inttypes.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "rand_split.h"
+
+static int failures = 0;
+
+static void check(const char *what, uint64_t got, uint64_t expected)
+{
+    if (got != expected) {
+        fprintf(stderr, "%s: got 0x%016" PRIx64 ", expected 0x%016" PRIx64 "\n",
+                what, got, expected);
+        failures++;
+    }
+}
+
+/* The outputs of a generator seeded with bytes 0 to 31: its first
+ * u64, the first u64 of a split off it, and then the first u64 of a
+ * PRF's generator at index 7. */
+static const uint64_t SIPRNG_KNOWN_ANSWERS[3] = {
+    UINT64_C(0xb6ef6ba3af59e55d),
+    UINT64_C(0x6dbe39b14105e60a),
+    UINT64_C(0x8bbe40357f8d2be3),
+};
+
+static const uint64_t CHASKEYRNG_KNOWN_ANSWERS[3] = {
+    UINT64_C(0xa31305a4dc61ebd3),
+    UINT64_C(0x6da07d1514d297ae),
+    UINT64_C(0xc4a49cd20852db0f),
+};
+
+static void test_siprng(const uint8_t (*seed)[32])
+{
+    SipRng *rng = rand_split_siprng_new(seed);
+    SipRng *copy = rand_split_siprng_clone(rng);
+    check("siprng next_u64", rand_split_siprng_next_u64(rng), SIPRNG_KNOWN_ANSWERS[0]);
+
+    SipRng *child = rand_split_siprng_split(rng);
+    check("siprng split", rand_split_siprng_next_u64(child), SIPRNG_KNOWN_ANSWERS[1]);
+
+    SipPrf *prf = rand_split_siprng_splitn(rng);
+    SipRng *at7 = rand_split_sipprf_call(prf, 7);
+    SipRng *at7_again = rand_split_sipprf_call(prf, 7);
+    check("siprng prf_call", rand_split_siprng_next_u64(at7), SIPRNG_KNOWN_ANSWERS[2]);
+    check("siprng prf_call again", rand_split_siprng_next_u64(at7_again),
+          SIPRNG_KNOWN_ANSWERS[2]);
+
+    /* fill_bytes gives the same bytes as next_u64, little-endian on
+     * every target, and a clone replays the stream. */
+    uint8_t bytes[8];
+    rand_split_siprng_fill_bytes(copy, bytes, sizeof bytes);
+    uint64_t word = 0;
+    for (int i = 7; i >= 0; i--) {
+        word = (word << 8) | bytes[i];
+    }
+    check("siprng fill_bytes", word, SIPRNG_KNOWN_ANSWERS[0]);
+
+    rand_split_siprng_free(rng);
+    rand_split_siprng_free(copy);
+    rand_split_siprng_free(child);
+    rand_split_siprng_free(at7);
+    rand_split_siprng_free(at7_again);
+    rand_split_sipprf_free(prf);
+    rand_split_siprng_free(NULL);
+}
+
+static void test_chaskeyrng(const uint8_t (*seed)[32])
+{
+    ChaskeyRng *rng = rand_split_chaskeyrng_new(seed);
+    check("chaskeyrng next_u64", rand_split_chaskeyrng_next_u64(rng),
+          CHASKEYRNG_KNOWN_ANSWERS[0]);
+
+    ChaskeyRng *child = rand_split_chaskeyrng_split(rng);
+    check("chaskeyrng split", rand_split_chaskeyrng_next_u64(child),
+          CHASKEYRNG_KNOWN_ANSWERS[1]);
+
+    ChaskeyPrf *prf = rand_split_chaskeyrng_splitn(rng);
+    ChaskeyRng *at7 = rand_split_chaskeyprf_call(prf, 7);
+    check("chaskeyrng prf_call", rand_split_chaskeyrng_next_u64(at7),
+          CHASKEYRNG_KNOWN_ANSWERS[2]);
+
+    rand_split_chaskeyrng_free(rng);
+    rand_split_chaskeyrng_free(child);
+    rand_split_chaskeyrng_free(at7);
+    rand_split_chaskeyprf_free(prf);
+}
+
+int main(void)
+{
+    uint8_t seed[32];
+    for (int i = 0; i < 32; i++) {
+        seed[i] = (uint8_t) i;
+    }
+
+    if (rand_split_siprng_new(NULL) != NULL) {
+        fprintf(stderr, "siprng new: expected NULL for a NULL seed\n");
+        failures++;
+    }
+    test_siprng(&seed);
+    test_chaskeyrng(&seed);
+
+    if (failures > 0) {
+        fprintf(stderr, "%d failures\n", failures);
+        return EXIT_FAILURE;
+    }
+    printf("ok\n");
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,182 @@
+//! Golden vectors for the versioned constructors.  Programs store
+//! seeds and expect them to give the same data in every release, so
+//! **these values must never change**.  A change to a generator's
+//! output is a new version, with its own constructor and vectors.
+
+extern crate rand;
+extern crate rand_split;
+
+use rand::{Isaac64Rng, Rng};
+use rand_split::{Split, SplitRng, SplitPrf};
+use rand_split::chaskeyrng::ChaskeyRng;
+use rand_split::seed::Seed;
+use rand_split::siprng::SipRng;
+use rand_split::twolcg::TwoLcgRng;
+
+
+/// Bytes 0 to 31.
+fn golden_seed() -> Seed {
+    let mut seed = [0u8; 32];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    seed
+}
+
+/// The first `u64` outputs of a generator.
+fn first<R: Rng>(rng: &mut R, n: usize) -> Vec<u64> {
+    (0..n).map(|_| rng.next_u64()).collect()
+}
+
+/// The stream that the golden vectors record, as `u64`s:
+///
+/// * 0–3: the root's first four outputs;
+/// * 4–5: the first two of a child split off it;
+/// * 6–7: the root's next two, after the split;
+/// * 8–12: the first output of a PRF split off the root at `call(0)`,
+///   `call(u32::MAX)`, `call_u64(1 << 40)`, `call_u128(1 << 100)`
+///   and `fold_in("golden")`.
+fn stream<R: SplitRng>(mut rng: R) -> Vec<u64> {
+    let mut result = first(&mut rng, 4);
+    result.extend(first(&mut rng.split(), 2));
+    result.extend(first(&mut rng, 2));
+    let prf = rng.splitn();
+    result.push(prf.call(0).next_u64());
+    result.push(prf.call(u32::MAX).next_u64());
+    result.push(prf.call_u64(1 << 40).next_u64());
+    result.push(prf.call_u128(1 << 100).next_u64());
+    result.push(prf.fold_in("golden").next_u64());
+    result
+}
+
+/// A value built from tuples and arrays, as `SplitRand` generates it.
+type Composite = (u32, [u8; 4], (u64, bool), [(u16, char); 2]);
+
+#[test]
+fn test_siprng_v1() {
+    assert_eq!(stream(SipRng::v1(golden_seed())), SIPRNG_V1);
+}
+
+#[test]
+fn test_chaskeyrng_v1() {
+    assert_eq!(stream(ChaskeyRng::v1(golden_seed())), CHASKEYRNG_V1);
+}
+
+#[test]
+fn test_twolcgrng_v1() {
+    assert_eq!(stream(TwoLcgRng::v1(golden_seed())), TWOLCGRNG_V1);
+}
+
+#[test]
+fn test_split_isaac64_v1() {
+    let rng: Split<Isaac64Rng> = Split::v1(golden_seed());
+    assert_eq!(stream(rng), SPLIT_ISAAC64_V1);
+}
+
+#[test]
+fn test_siprng_composite_v1() {
+    let composite: Composite = SipRng::v1(golden_seed()).split_gen();
+    assert_eq!(composite, SIPRNG_COMPOSITE_V1);
+}
+
+#[test]
+fn test_chaskeyrng_composite_v1() {
+    let composite: Composite = ChaskeyRng::v1(golden_seed()).split_gen();
+    assert_eq!(composite, CHASKEYRNG_COMPOSITE_V1);
+}
+
+#[test]
+fn test_twolcgrng_composite_v1() {
+    let composite: Composite = TwoLcgRng::v1(golden_seed()).split_gen();
+    assert_eq!(composite, TWOLCGRNG_COMPOSITE_V1);
+}
+
+#[test]
+fn test_split_isaac64_composite_v1() {
+    let mut rng: Split<Isaac64Rng> = Split::v1(golden_seed());
+    let composite: Composite = rng.split_gen();
+    assert_eq!(composite, SPLIT_ISAAC64_COMPOSITE_V1);
+}
+
+#[test]
+fn test_pointer_sized_v1() {
+    // The same on 32-bit and 64-bit targets.
+    let ints: (usize, isize) = SipRng::v1(golden_seed()).split_gen();
+    assert_eq!(ints, POINTER_SIZED_V1);
+}
+
+const SIPRNG_V1: [u64; 13] = [
+    0xb6ef6ba3af59e55d,
+    0x501a964ed6dd80ba,
+    0x6e92ff6de6c6d041,
+    0x5181f0437040360b,
+    0xf1a888ae6136066d,
+    0x2434952231909cb6,
+    0x110040804c803b3b,
+    0x368958867fb10127,
+    0x45f83ef22517e916,
+    0x19f20aa0e125d517,
+    0x9c2cfd6f2b4a4dc2,
+    0xd8ffd259984661f9,
+    0xe9eb833de75deaa5,
+];
+const CHASKEYRNG_V1: [u64; 13] = [
+    0xa31305a4dc61ebd3,
+    0x3018710a8d37111d,
+    0xf001cae4e4ad1fc5,
+    0x69fc3da842881991,
+    0x3713ec3feaf221be,
+    0x943bb0f53c897e6a,
+    0x9b96b0e15f6ea872,
+    0x80ef03ada18f5f68,
+    0x66036bdf616db7ef,
+    0xf2999efead579b60,
+    0x5b465577091b59d9,
+    0x648ba5f96b80ad6a,
+    0xae2528ec20831b2f,
+];
+const TWOLCGRNG_V1: [u64; 13] = [
+    0xb435a6b9ad92d401,
+    0x6b829722ef5ae4e4,
+    0xc51e4f629e125a9f,
+    0x4104ff39507a1eb4,
+    0x802bf1990fb88b6f,
+    0x44e27df658046d39,
+    0x8c2f19944ac64432,
+    0x4a6a88b4efade48a,
+    0x1059769a13632a36,
+    0x2d277552a81404bb,
+    0x49787016ca51ec49,
+    0x04ae1d8599c11927,
+    0x592ef68d5524e5e6,
+];
+const SPLIT_ISAAC64_V1: [u64; 13] = [
+    0xf71d07c5188efc26,
+    0x023ca46ea8ad0d76,
+    0x35b55e2ffa832754,
+    0x91113c00858180a9,
+    0x0c2fe3fbf22743ea,
+    0x24258612227d3435,
+    0x9f5741780c44b63e,
+    0xf47af10d462bbcee,
+    0xb0c62017b0a719be,
+    0xbc0190be396924e8,
+    0x7d61780ed84e19e7,
+    0xb29527af16169d53,
+    0xba5574bb2fa1c815,
+];
+
+const SIPRNG_COMPOSITE_V1: Composite =
+    (0x3eeaa33b, [0xff, 0x48, 0x85, 0xd2], (0xedb2d6f12a1bc0e8, false),
+     [(0xb60f, '\u{12855}'), (0x4c80, '\u{b2e49}')]);
+const CHASKEYRNG_COMPOSITE_V1: Composite =
+    (0x83fa4fd0, [0xf0, 0x01, 0xe9, 0x3c], (0x9d718091ebf38cb2, false),
+     [(0x8e13, '\u{45f2f}'), (0x1a92, '\u{c69cb}')]);
+const TWOLCGRNG_COMPOSITE_V1: Composite =
+    (0xbb11d8bf, [0x01, 0xcc, 0x06, 0x05], (0x2d962b6434ea59cc, false),
+     [(0x14f5, '\u{8a74}'), (0xc471, '\u{118e4}')]);
+const SPLIT_ISAAC64_COMPOSITE_V1: Composite =
+    (0x55baf711, [0x05, 0xe8, 0x77, 0xf0], (0x04545ff017baea14, true),
+     [(0xb30a, '\u{d2561}'), (0x97da, '\u{37dc0}')]);
+
+const POINTER_SIZED_V1: (usize, isize) = (0x3eeaa33b, 0x7f83e2cb);